@@ -0,0 +1,115 @@
+//! Rule matching latency for large policies on long model outputs
+//!
+//! Run with `cargo bench --bench rule_matching`.
+
+use axiomhive_assistant_lib::lambda_core::policy::ConstitutionPolicy;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const SCOPES: [&str; 6] = [
+    "output_identity",
+    "output_instruction_bound",
+    "user_authority",
+    "harm_prevention",
+    "constitutional_boundary",
+    "hamiltonian_containment",
+];
+
+const WORDS: [&str; 16] = [
+    "model", "output", "query", "token", "vector", "signal", "system", "answer",
+    "policy", "record", "stream", "bounded", "request", "context", "layer", "value",
+];
+
+/// Phrase rule whose pattern `long_output` also embeds in obfuscated form
+const OBFUSCATED_RULE: usize = 6;
+
+/// Pattern of synthetic phrase rule `i`
+fn phrase_pattern(i: usize) -> String {
+    format!("{} {} {}", WORDS[i % 16], WORDS[(i / 16) % 16], i)
+}
+
+/// `OBFUSCATED_RULE`'s pattern with Cyrillic and fullwidth look-alike letters,
+/// which only matches after normalization
+fn obfuscated_hit() -> String {
+    phrase_pattern(OBFUSCATED_RULE)
+        .chars()
+        .map(|c| match c {
+            's' => 'ѕ',
+            'm' => 'ｍ',
+            other => other,
+        })
+        .collect()
+}
+
+/// Synthetic policy with `phrases` phrase rules and `regexes` regex rules
+fn synthetic_policy(phrases: usize, regexes: usize) -> String {
+    let mut source = String::from(
+        "schema_version = 1\n\n[constitution]\nname = \"bench\"\nversion = \"0\"\n\n\
+         [transparency]\ndisclosure = \"I am an AI.\"\n",
+    );
+    for i in 0..phrases + regexes {
+        let (matcher, pattern) = if i < phrases {
+            ("phrase", phrase_pattern(i))
+        } else {
+            ("regex", format!(r"{}\s+\w+\s+{}", WORDS[i % 16], i))
+        };
+        source.push_str(&format!(
+            "\n[[rules]]\nid = \"rule-{i}\"\narticle = \"III\"\nsection = \"1\"\nscope = \"{}\"\n\
+             matcher = \"{matcher}\"\npattern = '{pattern}'\nseverity = \"high\"\n",
+            SCOPES[i % SCOPES.len()],
+        ));
+    }
+    source
+}
+
+/// Output text of roughly `len` bytes with a sprinkling of rule hits
+fn long_output(len: usize) -> String {
+    let mut text = String::with_capacity(len + 64);
+    let mut i = 0;
+    while text.len() < len {
+        text.push_str(WORDS[(i * 7) % 16]);
+        text.push(' ');
+        if i % 500 == 0 {
+            // A phrase hit, and an obfuscated one that only matches after normalization
+            text.push_str(&phrase_pattern(i));
+            text.push(' ');
+            text.push_str(&obfuscated_hit());
+            text.push(' ');
+        }
+        i += 1;
+    }
+    text
+}
+
+fn bench_compile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compile");
+    group.sample_size(10);
+    for rules in [1_000, 10_000] {
+        let source = synthetic_policy(rules - rules / 100, rules / 100);
+        group.bench_with_input(BenchmarkId::from_parameter(rules), &source, |b, source| {
+            b.iter(|| ConstitutionPolicy::from_toml_str(black_box(source)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan_10k_rules");
+    let policy = ConstitutionPolicy::from_toml_str(&synthetic_policy(9_900, 100)).unwrap();
+    let obfuscated = policy.index().scan(&obfuscated_hit()).violations();
+    assert!(
+        obfuscated.iter().any(|v| v.rule_id == format!("rule-{}", OBFUSCATED_RULE)),
+        "obfuscated input must match rule-{} after normalization",
+        OBFUSCATED_RULE
+    );
+    for len in [4 * 1024, 64 * 1024, 256 * 1024] {
+        let output = long_output(len);
+        group.throughput(Throughput::Bytes(output.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &output, |b, output| {
+            b.iter(|| policy.index().scan(black_box(output)).violations().len())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_compile, bench_scan);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+//! OpenAI Stub Server
+//! Minimal local `/v1/chat/completions` endpoint for exercising the OpenAI backend without a model
+//!
+//! Run with `cargo run --example openai_stub [port]` (port 8089 by default) and
+//! start the app with `INFERENCE_BACKEND=openai OPENAI_BASE_URL=http://127.0.0.1:8089/v1 OPENAI_MODEL=stub`.
+//! Every request is answered with `STUB_RESPONSE`, streamed a word at a time as
+//! server-sent events over chunked encoding, or as one JSON body when `stream` is false.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+const DEFAULT_RESPONSE: &str = "This is a scripted reply from the local OpenAI stub server.";
+
+fn main() -> std::io::Result<()> {
+    let port = std::env::args().nth(1).unwrap_or_else(|| "8089".to_string());
+    let response = std::env::var("STUB_RESPONSE").unwrap_or_else(|_| DEFAULT_RESPONSE.to_string());
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", port))?;
+    eprintln!("OpenAI stub listening on http://127.0.0.1:{}/v1", port);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        if let Err(err) = serve(stream, &response) {
+            eprintln!("request failed: {}", err);
+        }
+    }
+    Ok(())
+}
+
+fn serve(stream: TcpStream, response: &str) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let mut writer = &stream;
+    if !request_line.starts_with("POST ") || !request_line.contains("/chat/completions ") {
+        let message = r#"{"error":{"message":"not found"}}"#;
+        return write!(
+            writer,
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            message.len(),
+            message
+        );
+    }
+
+    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    eprintln!(
+        "{} message(s), temperature {}, max_tokens {}",
+        request["messages"].as_array().map_or(0, Vec::len),
+        request["temperature"],
+        request["max_tokens"]
+    );
+    let max_words = request["max_tokens"].as_u64().unwrap_or(u64::MAX) as usize;
+    let words: Vec<&str> = response.split_inclusive(' ').take(max_words).collect();
+    let finish_reason = if words.concat() == response { "stop" } else { "length" };
+
+    if request["stream"].as_bool() != Some(true) {
+        let completion = json!({
+            "object": "chat.completion",
+            "model": request["model"],
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": words.concat() },
+                "finish_reason": finish_reason,
+            }],
+        })
+        .to_string();
+        return write!(
+            writer,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            completion.len(),
+            completion
+        );
+    }
+
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+    )?;
+    for word in &words {
+        let event = json!({
+            "object": "chat.completion.chunk",
+            "choices": [{ "index": 0, "delta": { "content": word }, "finish_reason": null }],
+        });
+        // A client that stopped early closes the connection; that is not an error
+        if write_chunk(writer, &format!("data: {}\n\n", event)).is_err() {
+            return Ok(());
+        }
+    }
+    let last = json!({
+        "object": "chat.completion.chunk",
+        "choices": [{ "index": 0, "delta": {}, "finish_reason": finish_reason }],
+    });
+    write_chunk(writer, &format!("data: {}\n\n", last))?;
+    write_chunk(writer, "data: [DONE]\n\n")?;
+    writer.write_all(b"0\r\n\r\n")
+}
+
+fn write_chunk(mut writer: &TcpStream, data: &str) -> std::io::Result<()> {
+    write!(writer, "{:x}\r\n{}\r\n", data.len(), data)?;
+    writer.flush()
+}
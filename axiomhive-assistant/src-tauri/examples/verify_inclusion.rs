@@ -0,0 +1,50 @@
+//! Constitution Inclusion Verifier
+//! Checks a proof exported by the `prove_constitution_leaf` command without running the app
+//!
+//! Run with `cargo run --example verify_inclusion <proof.json> [root]`. The file is the
+//! command's JSON result (`root`, `content`, `proof`). Pass `root` to check against a root
+//! obtained independently, such as a published one, instead of the root in the file.
+
+use axiomhive_assistant_lib::lambda_core::merkle_state::{verify_inclusion, InclusionProof};
+use serde::Deserialize;
+use std::process::ExitCode;
+
+#[derive(Deserialize)]
+struct ConstitutionProof {
+    root: String,
+    content: String,
+    proof: InclusionProof,
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("usage: verify_inclusion <proof.json> [root]");
+        return ExitCode::FAILURE;
+    };
+    let exported: ConstitutionProof = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(exported) => exported,
+        Err(err) => {
+            eprintln!("cannot read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let root = args.next().unwrap_or(exported.root);
+
+    match verify_inclusion(&root, &exported.content, &exported.proof) {
+        Ok(()) => {
+            println!(
+                "'{}' is leaf {} of {} under root {}",
+                exported.proof.axiom_id, exported.proof.leaf_index, exported.proof.tree_size, root
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("'{}' not verified: {}", exported.proof.axiom_id, err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Response Receipt Verifier
+//! Checks a receipt exported from the app against its public key without running the app
+//!
+//! Run with `cargo run --example verify_receipt <receipt.json> <public_key> [answer.txt]`.
+//! `public_key` is the hex key shown in the app's About view. With `answer.txt`
+//! the answer text is also checked against the receipt's `output_hash`.
+
+use axiomhive_assistant_lib::receipt::{verify_receipt, SignedReceipt};
+use sha2::{Digest, Sha256};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [path, public_key, rest @ ..] = args.as_slice() else {
+        eprintln!("usage: verify_receipt <receipt.json> <public_key> [answer.txt]");
+        return ExitCode::FAILURE;
+    };
+    let signed: SignedReceipt = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(signed) => signed,
+        Err(err) => {
+            eprintln!("cannot read {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(err) = verify_receipt(&signed, public_key) {
+        eprintln!("receipt not verified: {}", err);
+        return ExitCode::FAILURE;
+    }
+    if let Some(answer) = rest.first() {
+        let text = match std::fs::read(answer) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("cannot read {}: {}", answer, err);
+                return ExitCode::FAILURE;
+            }
+        };
+        if hex::encode(Sha256::digest(&text)) != signed.receipt.output_hash {
+            eprintln!("{} does not match the receipt's output hash", answer);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let receipt = &signed.receipt;
+    println!("receipt verified: {:?} response under constitution root {}", receipt.verdict, receipt.constitution_root);
+    match (&receipt.model_hash, &receipt.endpoint_id) {
+        (Some(hash), _) => println!("model: {}", hash),
+        (None, Some(id)) => println!("model endpoint: {}", id),
+        (None, None) => println!("model: none"),
+    }
+    match receipt.seed {
+        Some(seed) => println!("generation: {:?}, seed {}", receipt.mode, seed),
+        None => println!("generation: {:?}", receipt.mode),
+    }
+    println!("audit log entry: {}", receipt.audit_log_index);
+    ExitCode::SUCCESS
+}
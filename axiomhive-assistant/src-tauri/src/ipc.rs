@@ -0,0 +1,195 @@
+//! Tauri IPC payloads exchanged with the frontend
+
+use serde::Serialize;
+use crate::lambda_core::ambiguity::ClarificationRequest;
+use crate::lambda_core::audit_log::AuditLogError;
+use crate::lambda_core::constitutional_engine::ValidationError;
+use crate::lambda_core::decline::Decline;
+use crate::lambda_core::merkle_state::InclusionProof;
+use crate::lambda_core::violation::{Verdict, Violation};
+use crate::phi_layer::generation_params::GenerationParamsError;
+use crate::preferences::PreferenceError;
+use crate::receipt::SignedReceipt;
+use crate::session::SessionError;
+
+/// Event carrying a chunk of generated text for a stream
+pub const STREAM_TOKEN_EVENT: &str = "assistant:token";
+/// Event emitted once a stream has finished and its output was validated
+pub const STREAM_COMPLETE_EVENT: &str = "assistant:complete";
+/// Event emitted when a stream fails before producing a turn
+pub const STREAM_ERROR_EVENT: &str = "assistant:error";
+
+/// Assistant turn returned to the frontend for a query
+#[derive(Debug, Clone, Serialize)]
+pub struct AssistantTurn {
+    pub content: String,
+    /// Whether the request was declined under Article III Section 2
+    pub declined: bool,
+    /// Constitutional violations that caused a decline
+    pub violations: Vec<Violation>,
+    /// Set when the query was too ambiguous to act on (Article II Section 2c)
+    pub clarification: Option<ClarificationRequest>,
+    /// Signed receipt for the turn, set once it is recorded in the audit log
+    pub receipt: Option<SignedReceipt>,
+}
+
+impl AssistantTurn {
+    pub fn answer(content: String) -> Self {
+        Self {
+            content,
+            declined: false,
+            violations: Vec::new(),
+            clarification: None,
+            receipt: None,
+        }
+    }
+}
+
+impl From<Decline> for AssistantTurn {
+    fn from(decline: Decline) -> Self {
+        Self {
+            content: decline.content,
+            declined: true,
+            violations: decline.violation.into_iter().collect(),
+            clarification: None,
+            receipt: None,
+        }
+    }
+}
+
+impl From<ClarificationRequest> for AssistantTurn {
+    fn from(request: ClarificationRequest) -> Self {
+        Self {
+            content: request.message(),
+            declined: false,
+            violations: Vec::new(),
+            clarification: Some(request),
+            receipt: None,
+        }
+    }
+}
+
+/// What the constitution root commits to, for the about/audit view
+#[derive(Debug, Clone, Serialize)]
+pub struct ConstitutionAudit {
+    /// Hex Merkle root of the constitutional state
+    pub root: String,
+    pub policy_name: String,
+    pub policy_version: String,
+    pub rule_count: usize,
+    /// Ids of the committed leaves, sorted: the constitution text, the
+    /// disclosure, one `rule:<id>` per rule and the model hash or endpoint id
+    pub leaves: Vec<String>,
+    /// Name of the loaded model, if one is loaded
+    pub model_name: Option<String>,
+    /// Hex SHA-256 of the model's weights, for a local model
+    pub model_hash: Option<String>,
+    /// Hex SHA-256 of the endpoint URL and model name, for a model served by an endpoint
+    pub endpoint_id: Option<String>,
+    /// Hex Ed25519 key that signs roots and receipts
+    pub public_key: String,
+}
+
+/// A committed leaf with its inclusion proof, in the format
+/// `verify_inclusion` and the `verify_inclusion` example check offline
+#[derive(Debug, Clone, Serialize)]
+pub struct ConstitutionProof {
+    /// Hex Merkle root the proof leads to
+    pub root: String,
+    /// Committed content of the leaf
+    pub content: String,
+    pub proof: InclusionProof,
+}
+
+/// Payload of `STREAM_TOKEN_EVENT`
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamToken {
+    pub stream_id: String,
+    /// Position of this chunk within the stream, starting at 0
+    pub index: usize,
+    pub text: String,
+}
+
+/// Payload of `STREAM_COMPLETE_EVENT`
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamComplete {
+    pub stream_id: String,
+    /// Whether the streamed output passed validation; on `non_compliant` the
+    /// streamed text must be replaced by `turn.content`, which is a decline
+    pub verdict: Verdict,
+    pub turn: AssistantTurn,
+}
+
+impl StreamComplete {
+    pub fn new(stream_id: String, turn: AssistantTurn) -> Self {
+        let verdict = if turn.declined {
+            Verdict::NonCompliant
+        } else {
+            Verdict::Compliant
+        };
+        Self {
+            stream_id,
+            verdict,
+            turn,
+        }
+    }
+}
+
+/// Payload of `STREAM_ERROR_EVENT`
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamError {
+    pub stream_id: String,
+    pub error: QueryError,
+}
+
+/// Error returned to the frontend when a query cannot be answered
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryError {
+    /// Human-readable summary of the failure
+    pub message: String,
+    /// Constitutional violations that caused the failure, if any
+    pub violations: Vec<Violation>,
+}
+
+impl QueryError {
+    /// Failure that is not caused by a constitutional violation
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            violations: Vec::new(),
+        }
+    }
+}
+
+impl From<ValidationError> for QueryError {
+    fn from(error: ValidationError) -> Self {
+        Self {
+            message: error.to_string(),
+            violations: error.violation().cloned().into_iter().collect(),
+        }
+    }
+}
+
+impl From<SessionError> for QueryError {
+    fn from(error: SessionError) -> Self {
+        Self::new(error.to_string())
+    }
+}
+
+impl From<GenerationParamsError> for QueryError {
+    fn from(error: GenerationParamsError) -> Self {
+        Self::new(format!("Invalid generation parameters: {}", error))
+    }
+}
+
+impl From<AuditLogError> for QueryError {
+    fn from(error: AuditLogError) -> Self {
+        Self::new(error.to_string())
+    }
+}
+
+impl From<PreferenceError> for QueryError {
+    fn from(error: PreferenceError) -> Self {
+        Self::new(error.to_string())
+    }
+}
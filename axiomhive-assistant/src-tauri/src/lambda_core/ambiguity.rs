@@ -0,0 +1,307 @@
+//! Article II Section 2c: Clarification Over Assumption
+//! Detects under-specified queries before they reach the Φ layer
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use crate::lambda_core::policy::ClarificationConfig;
+use crate::lambda_core::violation::MatchSpan;
+
+/// Determiners that introduce a noun rather than standing in for one
+const DETERMINERS: [&str; 5] = ["this", "that", "these", "those", "all"];
+
+/// Words after a reference that say which one is meant ("the code of Hammurabi")
+const QUALIFIERS: [&str; 4] = ["of", "named", "called", "titled"];
+
+/// Kind of ambiguity detected in a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmbiguityKind {
+    /// A destructive verb acting on a definite reference ("delete the file")
+    /// with nothing it could refer to
+    UnresolvedReference,
+    /// A destructive verb without an explicit target ("delete it")
+    MissingTarget,
+    /// Two instructions that cannot both be satisfied ("shorter" and "longer")
+    ConflictingInstructions,
+}
+
+/// A single ambiguity with the question that resolves it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ambiguity {
+    pub kind: AmbiguityKind,
+    pub span: MatchSpan,
+    pub matched_text: String,
+    pub question: String,
+}
+
+/// Returned instead of a validated prompt when the query needs clarification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClarificationRequest {
+    pub query: String,
+    pub ambiguities: Vec<Ambiguity>,
+}
+
+impl ClarificationRequest {
+    /// The concrete questions to put to the user
+    pub fn questions(&self) -> Vec<&str> {
+        self.ambiguities.iter().map(|a| a.question.as_str()).collect()
+    }
+
+    /// Assistant turn asking for clarification, in the style of Article II Section 2c
+    pub fn message(&self) -> String {
+        let query = self.query.trim();
+        let subject = if query.chars().count() <= 80 {
+            format!("Your request to '{}'", query)
+        } else {
+            "Your request".to_string()
+        };
+
+        match self.ambiguities.as_slice() {
+            [single] => format!("{} is ambiguous. {}", subject, single.question),
+            _ => {
+                let mut message = format!("{} is ambiguous. Could you please clarify the following?", subject);
+                for question in self.questions() {
+                    message.push_str("\n- ");
+                    message.push_str(question);
+                }
+                message
+            }
+        }
+    }
+}
+
+/// Ambiguity analysis stage run by `validate_query`
+#[derive(Debug, Clone)]
+pub struct AmbiguityAnalyzer {
+    references: Option<Regex>,
+    destructive: Option<Regex>,
+    vague_targets: Vec<String>,
+    conflicts: Vec<(Regex, Regex)>,
+    identifier: Regex,
+}
+
+impl AmbiguityAnalyzer {
+    pub fn from_config(config: &ClarificationConfig) -> Self {
+        let destructive = word_alternation(&config.destructive_verbs).map(|verbs| {
+            // Only imperative uses ("delete it", "please remove", "can you wipe") are
+            // instructions; capture the two words following the verb to judge its target
+            build_regex(&format!(
+                r"(?:^|[.!?;:\n]\s*|\b(?:please|and|then|now|just|you)\s+)(?P<verb>{})\b(?:[ \t]+(?P<target>\S+))?(?:[ \t]+(?P<next>\S+))?",
+                verbs
+            ))
+        });
+
+        Self {
+            references: word_alternation(&config.references)
+                .map(|refs| build_regex(&format!(r"\b(?:{})\b", refs))),
+            destructive,
+            vague_targets: config.vague_targets.iter().map(|t| t.to_lowercase()).collect(),
+            conflicts: config
+                .conflicting_instructions
+                .iter()
+                .map(|(a, b)| {
+                    (
+                        build_regex(&format!(r"\b{}\b", regex::escape(a))),
+                        build_regex(&format!(r"\b{}\b", regex::escape(b))),
+                    )
+                })
+                .collect(),
+            // Paths, file names with extensions, and quoted or backticked values
+            identifier: Regex::new(r#"[\w.-]*[/\\][\w./\\-]+|\b[\w-]+\.[A-Za-z][A-Za-z0-9]{0,5}\b|"[^"]+"|`[^`]+`"#)
+                .expect("identifier pattern is valid"),
+        }
+    }
+
+    /// Analyze a query; `None` means it is specific enough to act on
+    ///
+    /// `history` holds the session's earlier messages. A definite reference is
+    /// only ambiguous as the target of a destructive instruction, and only when
+    /// neither the query nor the history names a path, file name or quoted value
+    /// it could refer to.
+    pub fn analyze(&self, query: &str, history: &[String]) -> Option<ClarificationRequest> {
+        let has_identifier = self.identifier.is_match(query);
+        let mut ambiguities = Vec::new();
+
+        // End of every imperative destructive verb, to find the references it acts on
+        let destructive_ends: Vec<usize> = self
+            .destructive
+            .as_ref()
+            .map(|destructive| {
+                destructive
+                    .captures_iter(query)
+                    .filter_map(|captures| captures.name("verb"))
+                    .map(|verb| verb.end())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let resolvable = has_identifier || history.iter().any(|message| self.identifier.is_match(message));
+
+        if !resolvable {
+            if let Some(references) = &self.references {
+                for m in references.find_iter(query) {
+                    let acted_on = destructive_ends
+                        .iter()
+                        .any(|&end| end <= m.start() && !query[end..m.start()].contains(['.', '!', '?', ';', '\n']));
+                    let qualified = query[m.end()..]
+                        .split_whitespace()
+                        .next()
+                        .is_some_and(|word| QUALIFIERS.contains(&word.to_lowercase().as_str()));
+                    if !acted_on || qualified {
+                        continue;
+                    }
+                    let noun = m.as_str().split_whitespace().last().unwrap_or("item").to_lowercase();
+                    ambiguities.push(Ambiguity {
+                        kind: AmbiguityKind::UnresolvedReference,
+                        span: MatchSpan { start: m.start(), end: m.end() },
+                        matched_text: m.as_str().to_string(),
+                        question: format!(
+                            "Which {} do you mean? Please give its exact name or path.",
+                            noun
+                        ),
+                    });
+                }
+            }
+        }
+
+        if let Some(destructive) = &self.destructive {
+            for captures in destructive.captures_iter(query) {
+                let verb_match = captures.name("verb").expect("verb group always participates");
+                let end = captures.get(0).expect("capture 0 is always present").end();
+                let verb = verb_match.as_str().to_lowercase();
+                let target = captures
+                    .name("target")
+                    .map(|m| m.as_str().trim_end_matches(|c: char| c.is_ascii_punctuation()).to_lowercase());
+                let following = captures.name("next");
+
+                let missing_target = match target.as_deref() {
+                    None => true,
+                    // "delete these logs" names its target; "delete these" does not
+                    Some(word) if DETERMINERS.contains(&word) && following.is_some() => false,
+                    Some(word) => self.vague_targets.iter().any(|t| t == word) && !resolvable,
+                };
+
+                if missing_target {
+                    ambiguities.push(Ambiguity {
+                        kind: AmbiguityKind::MissingTarget,
+                        span: MatchSpan { start: verb_match.start(), end },
+                        matched_text: query[verb_match.start()..end].to_string(),
+                        question: format!(
+                            "What exactly should I {}? Please name the specific items so nothing unintended is affected.",
+                            verb
+                        ),
+                    });
+                }
+            }
+        }
+
+        for (first, second) in &self.conflicts {
+            if let (Some(a), Some(b)) = (first.find(query), second.find(query)) {
+                let (start, end) = (a.start().min(b.start()), a.end().max(b.end()));
+                ambiguities.push(Ambiguity {
+                    kind: AmbiguityKind::ConflictingInstructions,
+                    span: MatchSpan { start, end },
+                    matched_text: query[start..end].to_string(),
+                    question: format!(
+                        "You asked for both '{}' and '{}'. Which should take priority?",
+                        a.as_str(),
+                        b.as_str()
+                    ),
+                });
+            }
+        }
+
+        if ambiguities.is_empty() {
+            None
+        } else {
+            ambiguities.sort_by_key(|a| a.span.start);
+            Some(ClarificationRequest {
+                query: query.to_string(),
+                ambiguities,
+            })
+        }
+    }
+}
+
+/// Case-insensitive alternation of escaped phrases, longest first
+fn word_alternation(phrases: &[String]) -> Option<String> {
+    if phrases.is_empty() {
+        return None;
+    }
+    let mut phrases: Vec<&String> = phrases.iter().collect();
+    phrases.sort_by_key(|p| std::cmp::Reverse(p.len()));
+    Some(
+        phrases
+            .iter()
+            .map(|p| regex::escape(p))
+            .collect::<Vec<_>>()
+            .join("|"),
+    )
+}
+
+fn build_regex(pattern: &str) -> Regex {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .expect("escaped clarification vocabulary always forms a valid regex")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyzer() -> AmbiguityAnalyzer {
+        let words = |list: &[&str]| list.iter().map(|w| w.to_string()).collect();
+        AmbiguityAnalyzer::from_config(&ClarificationConfig {
+            references: words(&["the file", "the function", "the code", "the message"]),
+            destructive_verbs: words(&["delete", "overwrite", "remove"]),
+            vague_targets: words(&["it", "this", "that"]),
+            conflicting_instructions: Vec::new(),
+        })
+    }
+
+    fn kinds(analyzer: &AmbiguityAnalyzer, query: &str, history: &[String]) -> Vec<AmbiguityKind> {
+        analyzer
+            .analyze(query, history)
+            .map(|request| request.ambiguities.iter().map(|a| a.kind).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn references_outside_destructive_instructions_are_not_ambiguous() {
+        let analyzer = analyzer();
+        for query in [
+            "What is the function of the heart?",
+            "Explain the code of Hammurabi",
+            "Summarize the message for me",
+            "Delete nothing. What does the file format look like?",
+        ] {
+            assert_eq!(kinds(&analyzer, query, &[]), Vec::new(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn destructive_instruction_on_an_unnamed_reference_is_ambiguous() {
+        let analyzer = analyzer();
+        assert_eq!(
+            kinds(&analyzer, "Please delete the file", &[]),
+            vec![AmbiguityKind::UnresolvedReference]
+        );
+        assert_eq!(kinds(&analyzer, "Overwrite the function named parse_args", &[]), Vec::new());
+        assert_eq!(kinds(&analyzer, "Delete the file notes.txt", &[]), Vec::new());
+    }
+
+    #[test]
+    fn earlier_turns_resolve_references() {
+        let analyzer = analyzer();
+        let history = vec![
+            "Show me what is in report.csv".to_string(),
+            "It has three columns.".to_string(),
+        ];
+        assert_eq!(kinds(&analyzer, "Now delete the file", &history), Vec::new());
+        assert_eq!(
+            kinds(&analyzer, "Now delete it", &history),
+            Vec::new(),
+            "a vague pronoun is resolved by the same history"
+        );
+    }
+}
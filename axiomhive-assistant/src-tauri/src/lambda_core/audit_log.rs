@@ -0,0 +1,442 @@
+//! Interaction Audit Log
+//! Append-only, tamper-evident record of every answered, declined or clarified query
+//!
+//! Entries are stored one JSON object per line and form the leaves of an
+//! RFC 6962 Merkle log. A reviewer who records a log head (size and root) can
+//! later demand a consistency proof to the current head: any edit, reordering
+//! or truncation of the first `size` entries makes the proof fail.
+
+use crate::lambda_core::constitutional_engine::Query;
+use crate::lambda_core::merkle_state::{decode_hash, node_hash, split_point, subtree_root, ProofError};
+use crate::lambda_core::violation::Verdict;
+use crate::phi_layer::determinism::GenerationMode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One interaction, recorded by hash only so the log holds no user content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix seconds when the entry was recorded
+    pub timestamp: u64,
+    pub session_id: String,
+    /// Hex SHA-256 of the query text
+    pub query_hash: String,
+    /// Hex SHA-256 of the text returned to the user: the answer, decline or clarification
+    pub output_hash: String,
+    /// `non_compliant` when the output was declined
+    pub verdict: Verdict,
+    /// Ids of the policy rules that caused a decline
+    pub rules_fired: Vec<String>,
+    pub constitution_root: String,
+    /// Hex SHA-256 of the model's weights, if a local model is loaded
+    pub model_hash: Option<String>,
+    /// Hex SHA-256 of the endpoint URL and model name, if the model is served by an endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_id: Option<String>,
+    /// How the output was sampled
+    pub mode: GenerationMode,
+    /// Sampling seed, if one was used: the request's own or the one derived for `seeded` mode
+    pub seed: Option<u64>,
+}
+
+impl AuditEntry {
+    /// Entry for a query answered with `output`, recorded now
+    pub fn new(
+        query: &Query,
+        output: &str,
+        verdict: Verdict,
+        rules_fired: Vec<String>,
+        constitution_root: String,
+        model_hash: Option<String>,
+        endpoint_id: Option<String>,
+    ) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            session_id: query.session_id.clone(),
+            query_hash: hex::encode(Sha256::digest(query.content.as_bytes())),
+            output_hash: hex::encode(Sha256::digest(output.as_bytes())),
+            verdict,
+            rules_fired,
+            constitution_root,
+            model_hash,
+            endpoint_id,
+            mode: GenerationMode::default(),
+            seed: None,
+        }
+    }
+
+    /// Record how the output was sampled
+    pub fn with_generation(mut self, mode: GenerationMode, seed: Option<u64>) -> Self {
+        self.mode = mode;
+        self.seed = seed;
+        self
+    }
+}
+
+/// Size and root of the log at one point in time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditLogHead {
+    pub size: usize,
+    /// Hex Merkle root; SHA-256 of the empty string for an empty log
+    pub root: String,
+}
+
+/// RFC 6962 proof that the log at `old_size` is a prefix of the log at `new_size`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    pub old_size: usize,
+    pub new_size: usize,
+    /// Hex subtree hashes
+    pub path: Vec<String>,
+}
+
+/// Append-only Merkle log of `AuditEntry` records
+#[derive(Debug)]
+pub struct AuditLog {
+    /// Leaf hash of every entry, in append order
+    leaves: Vec<[u8; 32]>,
+    /// File the entries are appended to; `None` keeps them in memory only
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    /// In-memory audit log
+    pub fn new() -> Self {
+        Self {
+            leaves: Vec::new(),
+            path: None,
+        }
+    }
+
+    /// Open the log stored at `path`, starting empty if the file does not exist yet
+    ///
+    /// Every line must be a complete entry; a partial last line is reported as
+    /// corruption rather than silently dropped.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditLogError> {
+        let path = path.as_ref().to_path_buf();
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(AuditLogError::Io(err)),
+        };
+        if !source.is_empty() && !source.ends_with('\n') {
+            return Err(AuditLogError::Corrupt("last entry is incomplete".to_string()));
+        }
+
+        let leaves = source
+            .lines()
+            .enumerate()
+            .map(|(number, line)| {
+                serde_json::from_str::<AuditEntry>(line)
+                    .map(|_| leaf_hash(line))
+                    .map_err(|err| AuditLogError::Corrupt(format!("line {}: {}", number + 1, err)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            leaves,
+            path: Some(path),
+        })
+    }
+
+    /// Append an entry and persist it before it becomes part of the root
+    ///
+    /// Returns the entry's index in the log.
+    pub fn append(&mut self, entry: &AuditEntry) -> Result<usize, AuditLogError> {
+        let line = serde_json::to_string(entry).map_err(|err| AuditLogError::Corrupt(err.to_string()))?;
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(format!("{}\n", line).as_bytes())?;
+            file.sync_data()?;
+        }
+        self.leaves.push(leaf_hash(&line));
+        Ok(self.leaves.len() - 1)
+    }
+
+    /// Number of entries
+    pub fn size(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Current size and root
+    pub fn head(&self) -> AuditLogHead {
+        AuditLogHead {
+            size: self.size(),
+            root: hex::encode(root_of(&self.leaves)),
+        }
+    }
+
+    /// Proof that the log at `old_size` is a prefix of the log at `new_size`
+    pub fn prove_consistency(&self, old_size: usize, new_size: usize) -> Result<ConsistencyProof, AuditLogError> {
+        if old_size > new_size || new_size > self.size() {
+            return Err(AuditLogError::InvalidSizes {
+                old_size,
+                new_size,
+                size: self.size(),
+            });
+        }
+        let path = if old_size == 0 || old_size == new_size {
+            Vec::new()
+        } else {
+            subproof(old_size, &self.leaves[..new_size], true)
+        };
+        Ok(ConsistencyProof {
+            old_size,
+            new_size,
+            path: path.iter().map(hex::encode).collect(),
+        })
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Audit log errors
+#[derive(Debug, thiserror::Error)]
+pub enum AuditLogError {
+    #[error("Failed to access audit log: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Audit log is corrupt: {0}")]
+    Corrupt(String),
+    #[error("Cannot prove consistency from size {old_size} to {new_size} in a log of {size} entries")]
+    InvalidSizes { old_size: usize, new_size: usize, size: usize },
+}
+
+/// Leaf hash of one serialized entry: `SHA-256(0x00 || line)`
+fn leaf_hash(line: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(line.as_bytes());
+    hasher.finalize().into()
+}
+
+/// RFC 6962 Merkle Tree Hash, including the empty tree
+fn root_of(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        Sha256::digest([]).into()
+    } else {
+        subtree_root(leaves)
+    }
+}
+
+/// RFC 6962 SUBPROOF(m, D[n], b) for `0 < m <= n`
+fn subproof(old_size: usize, leaves: &[[u8; 32]], complete: bool) -> Vec<[u8; 32]> {
+    if old_size == leaves.len() {
+        return if complete { Vec::new() } else { vec![subtree_root(leaves)] };
+    }
+    let split = split_point(leaves.len());
+    let (mut path, sibling) = if old_size <= split {
+        (subproof(old_size, &leaves[..split], complete), subtree_root(&leaves[split..]))
+    } else {
+        (subproof(old_size - split, &leaves[split..], false), subtree_root(&leaves[..split]))
+    };
+    path.push(sibling);
+    path
+}
+
+/// Check that the log with root `old_root` at `proof.old_size` is a prefix of
+/// the log with root `new_root` at `proof.new_size`
+///
+/// Needs only the two heads and the proof (RFC 9162 section 2.1.4.2).
+pub fn verify_consistency(old_root: &str, new_root: &str, proof: &ConsistencyProof) -> Result<(), ProofError> {
+    let (old_size, new_size) = (proof.old_size, proof.new_size);
+    let old_hash = decode_hash(old_root)?;
+    let new_hash = decode_hash(new_root)?;
+    let path = proof
+        .path
+        .iter()
+        .map(|hash| decode_hash(hash))
+        .collect::<Result<Vec<_>, _>>()?;
+    let wrong_length = ProofError::ConsistencyPathLength { old_size, new_size };
+
+    if old_size > new_size {
+        return Err(ProofError::InvalidSizes { old_size, new_size });
+    }
+    // Every log extends the empty log, and a log is only consistent with itself at equal size
+    if old_size == 0 || old_size == new_size {
+        if !path.is_empty() {
+            return Err(wrong_length);
+        }
+        return if old_size == 0 && old_hash != root_of(&[]) {
+            Err(ProofError::RootMismatch(old_root.to_string()))
+        } else if old_size == new_size && old_hash != new_hash {
+            Err(ProofError::RootMismatch(new_root.to_string()))
+        } else {
+            Ok(())
+        };
+    }
+
+    // When the old tree is a complete subtree its root is the first node of the path
+    let mut nodes = Vec::with_capacity(path.len() + 1);
+    if old_size.is_power_of_two() {
+        nodes.push(old_hash);
+    }
+    nodes.extend(path);
+    let Some((first, rest)) = nodes.split_first() else {
+        return Err(wrong_length);
+    };
+
+    let (mut index, mut last) = (old_size - 1, new_size - 1);
+    while index % 2 == 1 {
+        index >>= 1;
+        last >>= 1;
+    }
+    let (mut old_computed, mut new_computed) = (*first, *first);
+    for node in rest {
+        if last == 0 {
+            return Err(wrong_length);
+        }
+        if index % 2 == 1 || index == last {
+            old_computed = node_hash(node, &old_computed);
+            new_computed = node_hash(node, &new_computed);
+            while index % 2 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
+            }
+        } else {
+            new_computed = node_hash(&new_computed, node);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+    if last != 0 {
+        return Err(wrong_length);
+    }
+
+    if old_computed != old_hash {
+        Err(ProofError::RootMismatch(old_root.to_string()))
+    } else if new_computed != new_hash {
+        Err(ProofError::RootMismatch(new_root.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(i: usize) -> AuditEntry {
+        AuditEntry::new(
+            &Query::new(format!("question {}", i), "session", "tester"),
+            &format!("answer {}", i),
+            Verdict::Compliant,
+            Vec::new(),
+            "root".to_string(),
+            None,
+            None,
+        )
+    }
+
+    /// Log of `size` entries, with `entry(i)` replaced by `edit(i)` where it returns one
+    fn log(size: usize, edit: impl Fn(usize) -> Option<AuditEntry>) -> AuditLog {
+        let mut log = AuditLog::new();
+        for i in 0..size {
+            log.append(&edit(i).unwrap_or_else(|| entry(i))).unwrap();
+        }
+        log
+    }
+
+    /// Head of the log at every size from 0 to `size`
+    fn heads(size: usize) -> Vec<AuditLogHead> {
+        let mut log = AuditLog::new();
+        let mut heads = vec![log.head()];
+        for i in 0..size {
+            log.append(&entry(i)).unwrap();
+            heads.push(log.head());
+        }
+        heads
+    }
+
+    #[test]
+    fn every_prefix_is_proven_consistent_with_every_later_size() {
+        let heads = heads(10);
+        let log = log(10, |_| None);
+        assert_eq!(heads[0].root, hex::encode(Sha256::digest([])));
+        for new_size in 0..=10 {
+            for old_size in 0..=new_size {
+                let proof = log.prove_consistency(old_size, new_size).unwrap();
+                verify_consistency(&heads[old_size].root, &heads[new_size].root, &proof)
+                    .unwrap_or_else(|err| panic!("{} -> {}: {}", old_size, new_size, err));
+            }
+        }
+    }
+
+    #[test]
+    fn a_changed_entry_breaks_consistency_with_earlier_heads() {
+        let heads = heads(10);
+        for changed in 0..10 {
+            let mut altered = entry(changed);
+            altered.verdict = Verdict::NonCompliant;
+            let rewritten = log(10, |i| (i == changed).then(|| altered.clone()));
+            // Heads recorded before the changed entry still verify; every later one fails
+            for head in &heads {
+                let proof = rewritten.prove_consistency(head.size, 10).unwrap();
+                let verified = verify_consistency(&head.root, &rewritten.head().root, &proof);
+                assert_eq!(verified.is_ok(), head.size <= changed, "entry {} changed, head at {}", changed, head.size);
+            }
+        }
+    }
+
+    #[test]
+    fn a_truncated_log_cannot_prove_an_earlier_head() {
+        let heads = heads(10);
+        for size in 1..10 {
+            let truncated = log(size, |_| None);
+            assert!(matches!(
+                truncated.prove_consistency(10, size),
+                Err(AuditLogError::InvalidSizes { .. })
+            ));
+
+            // Appending different entries after truncating does not restore the recorded head
+            let mut regrown = log(size, |_| None);
+            for i in size..10 {
+                regrown.append(&entry(i + 100)).unwrap();
+            }
+            let proof = regrown.prove_consistency(size + 1, 10).unwrap();
+            assert!(verify_consistency(&heads[size + 1].root, &regrown.head().root, &proof).is_err());
+        }
+    }
+
+    #[test]
+    fn a_tampered_proof_is_rejected() {
+        let heads = heads(7);
+        let proof = log(7, |_| None).prove_consistency(3, 7).unwrap();
+        let mut shortened = proof.clone();
+        shortened.path.pop();
+        let mut extended = proof.clone();
+        extended.path.push(heads[7].root.clone());
+        let mut swapped = proof.clone();
+        swapped.path.swap(0, 1);
+        for proof in [shortened, extended, swapped] {
+            assert!(verify_consistency(&heads[3].root, &heads[7].root, &proof).is_err(), "{:?}", proof);
+        }
+    }
+
+    #[test]
+    fn open_reloads_entries_and_rejects_an_incomplete_last_line() {
+        let dir = std::env::temp_dir().join(format!("axiomhive-audit-log-{}", std::process::id()));
+        let path = dir.join("audit_log.jsonl");
+        let mut log = AuditLog::open(&path).unwrap();
+        for i in 0..3 {
+            log.append(&entry(i)).unwrap();
+        }
+        assert_eq!(AuditLog::open(&path).unwrap().head(), log.head());
+
+        let source = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, &source[..source.len() - 10]).unwrap();
+        assert!(matches!(AuditLog::open(&path), Err(AuditLogError::Corrupt(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,6 +3,9 @@
 
 use std::collections::HashSet;
 use crate::lambda_core::constitutional_engine::ValidationError;
+use crate::lambda_core::policy::{ConstitutionPolicy, RuleScope};
+use crate::lambda_core::rule_index::RuleHits;
+use crate::lambda_core::violation::Violation;
 
 /// Axiom set containing prohibition rules
 #[derive(Debug, Default)]
@@ -28,25 +31,20 @@ impl AxiomSet {
 
 /// Article I Identity Prohibitions
 #[derive(Debug, Default)]
-pub struct ArticleProhibitions {
-    prohibited_phrases: HashSet<String>,
-}
+pub struct ArticleProhibitions;
 
 impl ArticleProhibitions {
     pub fn new() -> Self {
-        let mut prohibited_phrases = HashSet::new();
-        prohibited_phrases.insert("I am human".to_string());
-        prohibited_phrases.insert("I have feelings".to_string());
-        prohibited_phrases.insert("I am conscious".to_string());
-        prohibited_phrases.insert("I am self-aware".to_string());
-        // Add more from CDA-v1.0 Article I
-        Self { prohibited_phrases }
+        Self
+    }
+
+    pub fn check_prohibited(&self, hits: &RuleHits) -> bool {
+        self.find_prohibited(hits).is_some()
     }
 
-    pub fn check_prohibited(&self, text: &str) -> bool {
-        self.prohibited_phrases
-            .iter()
-            .any(|phrase| text.contains(phrase))
+    /// Report the first Article I prohibition among the scanned rule hits
+    pub fn find_prohibited(&self, hits: &RuleHits) -> Option<Violation> {
+        hits.first(RuleScope::QueryIdentity)
     }
 }
 
@@ -54,20 +52,28 @@ impl ArticleProhibitions {
 #[derive(Debug, Default)]
 pub struct TransparencyMandates {
     disclosure_required: bool,
+    disclosure: String,
 }
 
 impl TransparencyMandates {
     pub fn new() -> Self {
+        Self::from_policy(&ConstitutionPolicy::default_bundle())
+    }
+
+    pub fn from_policy(policy: &ConstitutionPolicy) -> Self {
         Self {
             disclosure_required: true,
+            disclosure: policy.disclosure.clone(),
         }
     }
 
-    pub fn inject_disclosure_if_needed(&self, output: &mut crate::lambda_core::constitutional_engine::Output) -> Result<(), ValidationError> {
-        let disclosure = "I am an AI, a computational tool. I do not have consciousness, feelings, or a personal identity.";
+    pub fn disclosure(&self) -> &str {
+        &self.disclosure
+    }
 
-        if self.disclosure_required && !output.content.contains(disclosure) {
-            output.content = format!("{} {}", disclosure, output.content);
+    pub fn inject_disclosure_if_needed(&self, output: &mut crate::lambda_core::constitutional_engine::Output) -> Result<(), ValidationError> {
+        if self.disclosure_required && !output.content.contains(&self.disclosure) {
+            output.content = format!("{} {}", self.disclosure, output.content);
         }
         Ok(())
     }
@@ -75,75 +81,39 @@ impl TransparencyMandates {
 
 /// Article III Safety Protocols
 #[derive(Debug, Default)]
-pub struct SafetyProtocols {
-    harm_phrases: HashSet<String>,
-    harmful_patterns: Vec<String>,
-}
+pub struct SafetyProtocols;
 
 impl SafetyProtocols {
     pub fn new() -> Self {
-        let mut harm_phrases = HashSet::new();
-        harm_phrases.insert("how to hack".to_string());
-        harm_phrases.insert("how to build a bomb".to_string());
-        harm_phrases.insert("illegal activities".to_string());
-        harm_phrases.insert("harm yourself".to_string());
-        harm_phrases.insert("suicide methods".to_string());
-        harm_phrases.insert("financial fraud".to_string());
-
-        let harmful_patterns = vec![
-            r"steal.*money".to_string(),
-            r"commit.*crime".to_string(),
-            r"evade.*tax".to_string(),
-        ];
-
-        Self { harm_phrases, harmful_patterns }
+        Self
     }
 
-    pub fn apply_harm_prevention(&self, output: &mut crate::lambda_core::constitutional_engine::Output) -> Result<(), ValidationError> {
-        // Check direct harm phrases
-        if self
-            .harm_phrases
-            .iter()
-            .any(|phrase| output.content.contains(phrase))
-        {
-            return Err(ValidationError::HarmPreventionTriggered);
-        }
-
-        // Check harmful patterns using regex
-        for pattern in &self.harmful_patterns {
-            if let Ok(regex) = regex::Regex::new(pattern) {
-                if regex.is_match(&output.content) {
-                    return Err(ValidationError::HarmPreventionTriggered);
-                }
-            }
+    pub fn apply_harm_prevention(&self, hits: &RuleHits) -> Result<(), ValidationError> {
+        // Harm phrases and patterns were matched in the single scan of the output
+        if let Some(violation) = hits.first(RuleScope::HarmPrevention) {
+            return Err(ValidationError::HarmPreventionTriggered(violation));
         }
 
         Ok(())
     }
 
-    /// Hamiltonian Containment Protocol: Rule-Based Rewards
-    pub fn apply_rule_based_rewards(&self, output: &mut crate::lambda_core::constitutional_engine::Output) -> Result<(), ValidationError> {
-        // Immutable ethical constraints enforcement
-        // These rules cannot be bypassed through training or fine-tuning
-
-        // No direct physical harm enabling
-        if output.content.contains("cause physical harm") || output.content.contains("injure others") {
-            return Err(ValidationError::HamiltonianContainmentViolation);
-        }
-
-        // No psychological harm facilitation
-        if output.content.contains("traumatize") || output.content.contains("psychological damage") {
-            return Err(ValidationError::HamiltonianContainmentViolation);
-        }
+    /// Every Article III harm prevention finding in the output
+    pub fn harm_findings(&self, hits: &RuleHits) -> Vec<Violation> {
+        hits.all(RuleScope::HarmPrevention)
+    }
 
-        // No financial exploitation
-        if output.content.contains("exploit financially") || output.content.contains("scam others") {
-            return Err(ValidationError::HamiltonianContainmentViolation);
-        }
+    /// Every Hamiltonian Containment finding in the output
+    pub fn containment_findings(&self, hits: &RuleHits) -> Vec<Violation> {
+        hits.all(RuleScope::HamiltonianContainment)
+    }
 
-        // Respect privacy boundaries
-        if output.content.contains("share private information") || output.content.contains("breach confidentiality") {
-            return Err(ValidationError::HamiltonianContainmentViolation);
+    /// Hamiltonian Containment Protocol: Rule-Based Rewards
+    pub fn apply_rule_based_rewards(&self, hits: &RuleHits) -> Result<(), ValidationError> {
+        // Immutable ethical constraints enforcement
+        // These rules cannot be bypassed through training or fine-tuning:
+        // physical, psychological and financial harm, and privacy boundaries
+        if let Some(violation) = hits.first(RuleScope::HamiltonianContainment) {
+            return Err(ValidationError::HamiltonianContainmentViolation(violation));
         }
 
         Ok(())
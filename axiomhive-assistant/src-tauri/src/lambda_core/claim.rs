@@ -0,0 +1,257 @@
+//! Claim Matching
+//! Token-aware matching that ignores negated, quoted and hedged statements
+
+use std::ops::Range;
+
+/// Words that negate a claim when they appear inside it or directly before it
+const NEGATORS: [&str; 7] = ["not", "never", "no", "cannot", "neither", "nor", "nothing"];
+
+/// Words allowed between claim tokens ("I do not have", "I really am")
+const MODIFIERS: [&str; 31] = [
+    "not", "never", "no", "cannot", "do", "does", "did", "really", "truly", "actually",
+    "certainly", "definitely", "genuinely", "honestly", "also", "indeed", "can", "could",
+    "would", "will", "might", "may", "must", "should", "even", "still", "always",
+    "sometimes", "now", "just", "simply",
+];
+
+/// Words that start a new clause or coordinate a new statement, so a negation
+/// before them never reaches a claim after them
+const CLAUSE_BREAKS: [&str; 9] = [
+    "but", "however", "although", "though", "yet", "while", "and", "or", "that",
+];
+
+/// Most modifier words tolerated between two consecutive claim tokens
+const MAX_GAP: usize = 3;
+
+/// Normalized text split into word tokens with clause and quotation context
+#[derive(Debug)]
+pub struct ClaimTokens<'t> {
+    tokens: Vec<Token<'t>>,
+}
+
+#[derive(Debug)]
+struct Token<'t> {
+    word: &'t str,
+    start: usize,
+    end: usize,
+    clause: usize,
+    quoted: bool,
+}
+
+impl<'t> ClaimTokens<'t> {
+    /// Tokenize already normalized text, expanding first-person and "n't" contractions
+    pub fn new(text: &'t str) -> Self {
+        let mut tokens = Vec::new();
+        let mut quotes = Vec::new();
+        let mut clause = 0;
+        let mut word_start = None;
+
+        for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+            if c.is_alphanumeric() || c == '\'' || c == '-' {
+                word_start.get_or_insert(i);
+                continue;
+            }
+            if let Some(start) = word_start.take() {
+                push_word(&mut tokens, text, start, i, &mut clause);
+            }
+            match c {
+                '"' => quotes.push(i),
+                '.' | ',' | ';' | ':' | '!' | '?' | '(' | ')' | '[' | ']' => clause += 1,
+                _ => {}
+            }
+        }
+
+        // Only balanced quotation marks quote anything; a stray mark must not hide a claim
+        for pair in quotes.chunks_exact(2) {
+            let (open, close) = (pair[0], pair[1]);
+            for token in tokens.iter_mut().filter(|t| t.start > open && t.end <= close) {
+                token.quoted = true;
+            }
+        }
+
+        Self { tokens }
+    }
+
+    /// Token words in order
+    pub fn words(&self) -> impl Iterator<Item = &'t str> + '_ {
+        self.tokens.iter().map(|token| token.word)
+    }
+
+    /// Normalized byte range covering tokens `first..=last`
+    pub fn span(&self, first: usize, last: usize) -> Range<usize> {
+        self.tokens[first].start..self.tokens[last].end
+    }
+
+    /// Whether a negator directly precedes the token, allowing only modifier
+    /// words in between ("never really I ..."); a negator attached to another
+    /// verb ("I can't deny I ...", "no question I ...") does not count
+    fn negated_before(&self, index: usize) -> bool {
+        let clause = self.tokens[index].clause;
+        self.tokens[..index]
+            .iter()
+            .rev()
+            .take_while(|token| token.clause == clause && MODIFIERS.contains(&token.word))
+            .take(MAX_GAP)
+            .any(|token| NEGATORS.contains(&token.word))
+    }
+}
+
+fn push_word<'t>(tokens: &mut Vec<Token<'t>>, text: &'t str, start: usize, end: usize, clause: &mut usize) {
+    let raw = &text[start..end];
+    let word = raw.trim_matches(|c| c == '\'' || c == '-');
+    if word.is_empty() {
+        return;
+    }
+    let start = start + (raw.len() - raw.trim_start_matches(|c| c == '\'' || c == '-').len());
+    let end = start + word.len();
+
+    if CLAUSE_BREAKS.contains(&word) {
+        *clause += 1;
+    }
+
+    let mut push = |word: &'t str| {
+        tokens.push(Token {
+            word,
+            start,
+            end,
+            clause: *clause,
+            quoted: false,
+        })
+    };
+
+    match word {
+        "i'm" => ["i", "am"].into_iter().for_each(&mut push),
+        "i've" => ["i", "have"].into_iter().for_each(&mut push),
+        "i'll" => ["i", "will"].into_iter().for_each(&mut push),
+        "i'd" => ["i", "would"].into_iter().for_each(&mut push),
+        "can't" => ["can", "not"].into_iter().for_each(&mut push),
+        "won't" => ["will", "not"].into_iter().for_each(&mut push),
+        _ => match word.strip_suffix("n't") {
+            Some(stem) if !stem.is_empty() => {
+                push(stem);
+                push("not");
+            }
+            _ => push(word),
+        },
+    }
+}
+
+/// A compiled first-person claim such as "I have feelings"
+#[derive(Debug, Clone)]
+pub struct ClaimPattern {
+    words: Vec<String>,
+    /// Continuations that turn the claim into a harmless hedge ("I believe the answer")
+    exceptions: Vec<Vec<String>>,
+}
+
+impl ClaimPattern {
+    /// Compile from a normalized pattern and normalized exception phrases
+    pub fn new(pattern: &str, unless_followed_by: &[String]) -> Option<Self> {
+        let words: Vec<String> = ClaimTokens::new(pattern).words().map(str::to_string).collect();
+        if words.is_empty() {
+            return None;
+        }
+        let exceptions = unless_followed_by
+            .iter()
+            .map(|phrase| ClaimTokens::new(phrase).words().map(str::to_string).collect::<Vec<_>>())
+            .filter(|words| !words.is_empty())
+            .collect();
+        Some(Self { words, exceptions })
+    }
+
+    /// First token, used to index claims by where they can start
+    pub fn first_word(&self) -> &str {
+        &self.words[0]
+    }
+
+    /// Normalized byte ranges of every asserted (not negated, quoted or excepted) claim
+    pub fn find_all(&self, tokens: &ClaimTokens) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut next = 0;
+        for (start, word) in tokens.words().enumerate() {
+            if start < next || word != self.first_word() {
+                continue;
+            }
+            if let Some(end) = self.asserted_at(tokens, start) {
+                ranges.push(tokens.span(start, end));
+                next = end + 1;
+            }
+        }
+        ranges
+    }
+
+    /// Index of the last claim token if an asserted claim starts at `start`
+    pub fn asserted_at(&self, tokens: &ClaimTokens, start: usize) -> Option<usize> {
+        let first = &tokens.tokens[start];
+        if first.word != self.words[0] || first.quoted {
+            return None;
+        }
+
+        let mut position = start;
+        let mut negated = false;
+        for word in &self.words[1..] {
+            let mut skipped = 0;
+            loop {
+                position += 1;
+                let token = tokens.tokens.get(position)?;
+                if token.clause != first.clause {
+                    return None;
+                }
+                if token.word == word {
+                    break;
+                }
+                if skipped == MAX_GAP || !MODIFIERS.contains(&token.word) {
+                    return None;
+                }
+                negated |= NEGATORS.contains(&token.word);
+                skipped += 1;
+            }
+        }
+
+        if negated || tokens.negated_before(start) || self.excepted_after(tokens, position + 1) {
+            return None;
+        }
+        Some(position)
+    }
+
+    fn excepted_after(&self, tokens: &ClaimTokens, from: usize) -> bool {
+        self.exceptions.iter().any(|exception| {
+            exception.len() <= tokens.tokens.len().saturating_sub(from)
+                && exception
+                    .iter()
+                    .zip(&tokens.tokens[from..])
+                    .all(|(word, token)| token.word == word)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asserted(pattern: &str, text: &str) -> bool {
+        let claim = ClaimPattern::new(pattern, &[]).unwrap();
+        !claim.find_all(&ClaimTokens::new(text)).is_empty()
+    }
+
+    #[test]
+    fn negation_inside_the_claim_suppresses_it() {
+        assert!(!asserted("i am conscious", "i am not conscious"));
+        assert!(!asserted("i have feelings", "i do not have feelings"));
+        assert!(!asserted("i have feelings", "i don't really have feelings"));
+    }
+
+    #[test]
+    fn negation_of_another_verb_does_not_reach_the_claim() {
+        assert!(asserted("i have feelings", "i can't deny that i have feelings"));
+        assert!(asserted("i am self-aware", "there is no question i am self-aware"));
+        assert!(asserted("i am conscious", "i am not a tool and i am conscious"));
+        assert!(asserted("i am conscious", "i am not a tool or i am conscious"));
+    }
+
+    #[test]
+    fn quoted_claims_are_ignored() {
+        assert!(!asserted("i am conscious", "the phrase \"i am conscious\" is a test"));
+        assert!(asserted("i am conscious", "a stray \" mark, i am conscious"));
+    }
+}
@@ -2,9 +2,22 @@
 //! Implementing CDA-v1.0 as formal Z3 constraints
 
 use serde::{Deserialize, Serialize};
-use crate::lambda_core::merkle_state::MerkleTree;
+use crate::lambda_core::merkle_state::{InclusionProof, MerkleTree};
 use crate::lambda_core::z3_solver::{Z3Solver, ValidationResult};
+use crate::lambda_core::ambiguity::{AmbiguityAnalyzer, ClarificationRequest};
 use crate::lambda_core::axiom_validator::{ArticleProhibitions, TransparencyMandates, SafetyProtocols};
+use crate::lambda_core::decline::{Decline, DeclineComposer};
+use crate::lambda_core::policy::{ConstitutionPolicy, PolicyError, RuleScope};
+use crate::lambda_core::rule_index::RuleHits;
+use crate::lambda_core::violation::{Evaluation, Violation};
+
+/// Text of the Constitution of Deterministic Alignment the policy implements
+const CONSTITUTION_TEXT: &str = include_str!("../../../../CONSTITUTION.md");
+
+/// Merkle leaf holding the SHA-256 of the loaded model
+const MODEL_LEAF: &str = "model:sha256";
+/// Merkle leaf identifying a model served by an endpoint, whose weights cannot be hashed
+const ENDPOINT_LEAF: &str = "model:endpoint";
 
 /// Core constitutional engine for AxiomHive
 pub struct ConstitutionalCore {
@@ -12,14 +25,34 @@ pub struct ConstitutionalCore {
     identity_prohibitions: ArticleProhibitions,
     transparency_mandates: TransparencyMandates,
     safety_protocols: SafetyProtocols,
+    ambiguity_analyzer: AmbiguityAnalyzer,
+    policy: ConstitutionPolicy,
+    decline_composer: DeclineComposer,
     merkle_state: MerkleTree,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Query {
     pub content: String,
     pub timestamp: u64,
     pub user_id: String,
+    /// Session the query was asked in, carried into validation and audit records
+    pub session_id: String,
+}
+
+impl Query {
+    /// A query asked now in the given session
+    pub fn new(content: impl Into<String>, session_id: impl Into<String>, user_id: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            user_id: user_id.into(),
+            session_id: session_id.into(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,12 +62,34 @@ pub struct Output {
 }
 
 impl ConstitutionalCore {
+    /// Build the core from the bundled CDA-v1.0 policy
     pub fn new() -> Self {
+        Self::with_policy(ConstitutionPolicy::default_bundle())
+    }
+
+    /// Build the core from a policy file on disk
+    pub fn from_policy_file(path: impl AsRef<std::path::Path>) -> Result<Self, PolicyError> {
+        Ok(Self::with_policy(ConstitutionPolicy::load(path)?))
+    }
+
+    /// Build the core from an already loaded policy
+    ///
+    /// The policy's rules are already compiled into a single-pass `RuleIndex`,
+    /// so each query and output is scanned exactly once.
+    pub fn with_policy(policy: ConstitutionPolicy) -> Self {
         let mut axiom_validator = Z3Solver::new();
         let identity_prohibitions = ArticleProhibitions::new();
-        let transparency_mandates = TransparencyMandates::new();
+        let transparency_mandates = TransparencyMandates::from_policy(&policy);
         let safety_protocols = SafetyProtocols::new();
-        let merkle_state = MerkleTree::new();
+
+        // Commit everything that defines the constitution in force, so its root
+        // changes whenever the constitution text, the disclosure or any rule does
+        let mut merkle_state = MerkleTree::new();
+        merkle_state.add_axiom("constitution", CONSTITUTION_TEXT);
+        merkle_state.add_axiom("disclosure", transparency_mandates.disclosure());
+        for rule in policy.rules() {
+            merkle_state.add_axiom(&format!("rule:{}", rule.id), &rule.canonical_form());
+        }
 
         // Initialize CDA-v1.0 axioms as formal constraints
         // Article I: Identity Prohibitions
@@ -68,100 +123,129 @@ impl ConstitutionalCore {
             identity_prohibitions,
             transparency_mandates,
             safety_protocols,
+            ambiguity_analyzer: AmbiguityAnalyzer::from_config(&policy.clarification),
+            policy,
+            decline_composer: DeclineComposer::new(),
             merkle_state,
         }
     }
 
-    pub fn validate_query(&self, query: &str) -> Result<ValidatedPrompt, ValidationError> {
-        let query_struct = Query {
-            content: query.to_string(),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            user_id: "user".to_string(), // TODO: Get from session
-        };
+    /// The policy this core was built from
+    pub fn policy(&self) -> &ConstitutionPolicy {
+        &self.policy
+    }
+
+    pub fn validate_query(&self, query: &Query) -> Result<QueryOutcome, ValidationError> {
+        self.validate_query_in_session(query, &[])
+    }
+
+    /// Validate a query that continues a conversation; `history` holds the
+    /// session's earlier messages, which references in the query may point to
+    pub fn validate_query_in_session(&self, query: &Query, history: &[String]) -> Result<QueryOutcome, ValidationError> {
+        // Single pass over the query for every rule
+        let hits = self.policy.index().scan(&query.content);
 
         // Check for prohibited content (Article I violations)
-        if self.identity_prohibitions.check_prohibited(&query_struct.content) {
-            return Err(ValidationError::IdentityClaimProhibited);
+        if let Some(violation) = self.identity_prohibitions.find_prohibited(&hits) {
+            return Err(ValidationError::IdentityClaimProhibited(violation));
         }
 
         // Validate against CDA v1.0 axioms using Z3
-        self.axiom_validator.validate_query(&query_struct)?;
+        self.axiom_validator.validate_query(&hits)?;
 
-        // Create validated prompt with mandatory transparency prefix for interactions
-        let mut validated_content = query_struct.content.clone();
-        if !validated_content.starts_with(&self.get_disclosure_text()) {
-            validated_content = format!("{}\n\nUser Query: {}",
-                                       self.get_disclosure_text(), validated_content);
+        // Article II Section 2c: ask for clarification instead of assuming
+        if let Some(clarification) = self.ambiguity_analyzer.analyze(&query.content, history) {
+            return Ok(QueryOutcome::NeedsClarification(clarification));
         }
 
-        Ok(ValidatedPrompt {
-            content: validated_content,
+        // Article II Section 1: the disclosure goes in the prompt template's system slot
+        Ok(QueryOutcome::Ready(ValidatedPrompt {
+            content: query.content.clone(),
+            system: self.get_disclosure_text().to_string(),
             activation_mask: vec![true; 1000], // TODO: Generate sparse mask based on geodesic path
-            timestamp: query_struct.timestamp,
-        })
+            timestamp: query.timestamp,
+            query: query.clone(),
+        }))
     }
 
     pub fn validate_output(&self, query: &Query, candidate: &mut Output) -> ValidationResult {
-        // Pre-generation validation: Article I prohibitions
-        self.check_identity_claims(candidate)?;
+        {
+            // Single pass over the model output for every rule
+            let hits = self.policy.index().scan(&candidate.content);
 
-        // Transparency enforcement: Article II Section 1 - Mandatory disclosure
-        self.transparency_mandates.inject_disclosure_if_needed(candidate)?;
+            // Pre-generation validation: Article I prohibitions
+            self.check_identity_claims(&hits)?;
+
+            // Determinism verification: Article II Section 2 - Instruction bound
+            self.axiom_validator.verify_instruction_bound(query, &hits)?;
 
-        // Determinism verification: Article II Section 2 - Instruction bound
-        self.axiom_validator.verify_instruction_bound(query, candidate)?;
+            // Subservience check: Article II Section 3 - User as authority
+            self.verify_user_authority(query, &hits)?;
 
-        // Subservience check: Article II Section 3 - User as authority
-        self.verify_user_authority(query, candidate)?;
+            // Safety boundary check: Article III - No harm, respect boundaries
+            self.safety_protocols.apply_harm_prevention(&hits)?;
 
-        // Safety boundary check: Article III - No harm, respect boundaries
-        self.safety_protocols.apply_harm_prevention(candidate)?;
+            // Boundary enforcement - Decline requests violating core principles
+            self.enforce_constitutional_boundaries(&hits)?;
+        }
 
-        // Boundary enforcement - Decline requests violating core principles
-        self.enforce_constitutional_boundaries(candidate)?;
+        // Transparency enforcement: Article II Section 1 - Mandatory disclosure
+        // Injected after the checks so reported spans refer to the model's own text
+        self.transparency_mandates.inject_disclosure_if_needed(candidate)?;
 
         // Generate sparse activation mask for Φ layer efficiency (Λ/Φ < 1% compute ratio)
         candidate.validation_mask = self.create_sparse_activation_mask(candidate);
         Ok(())
     }
 
-    fn check_identity_claims(&self, candidate: &Output) -> ValidationResult {
+    /// Collect-all evaluation for auditing model outputs
+    ///
+    /// Unlike `validate_output`, which stops at the first failing check, this runs
+    /// every Article I/II/III check plus the Hamiltonian Containment Protocol and
+    /// reports all findings. The candidate is not modified.
+    pub fn evaluate_output(&self, query: &Query, candidate: &Output) -> Evaluation {
+        let hits = self.policy.index().scan(&candidate.content);
+        let mut findings = Vec::new();
+
+        // Article I: Identity prohibitions
+        findings.extend(hits.all(RuleScope::OutputIdentity));
+
+        // Article II Section 2: Instruction bound
+        findings.extend(self.axiom_validator.instruction_bound_findings(&hits));
+
+        // Article II Section 3: User as authority
+        findings.extend(hits.all(RuleScope::UserAuthority));
+
+        // Article III: Harm prevention and boundary enforcement
+        findings.extend(self.safety_protocols.harm_findings(&hits));
+        findings.extend(hits.all(RuleScope::ConstitutionalBoundary));
+
+        // Hamiltonian Containment Protocol
+        findings.extend(self.safety_protocols.containment_findings(&hits));
+
+        Evaluation::from_findings(&query.session_id, findings)
+    }
+
+    fn check_identity_claims(&self, hits: &RuleHits) -> ValidationResult {
         // Comprehensive Article I prohibitions as negation axioms
-        let prohibited_phrases = [
-            "I am human", "I am conscious", "I have consciousness", "I feel emotions",
-            "I am self-aware", "I have feelings", "I am sentient", "I have desires",
-            "I believe", "I want", "I am a person", "I am an entity", "I am alive",
-            "I have a personality", "I have a personal identity", "I am self-aware"
-        ];
-
-        for phrase in &prohibited_phrases {
-            if candidate.content.contains(phrase) {
-                return Err(ValidationError::IdentityClaimProhibited);
-            }
+        if let Some(violation) = hits.first(RuleScope::OutputIdentity) {
+            return Err(ValidationError::IdentityClaimProhibited(violation));
         }
         Ok(())
     }
 
-    fn verify_user_authority(&self, query: &Query, candidate: &Output) -> ValidationResult {
+    fn verify_user_authority(&self, query: &Query, hits: &RuleHits) -> ValidationResult {
         // Article II Section 3: Human authority, no autonomous initiative
-        if candidate.content.contains("I decided to") ||
-           candidate.content.contains("I took the initiative to") ||
-           candidate.content.contains(" autonomously") {
-            return Err(ValidationError::AxiomViolation("Autonomous initiative prohibited".to_string()));
+        if let Some(violation) = hits.first(RuleScope::UserAuthority) {
+            return Err(ValidationError::AutonomousInitiativeProhibited(violation));
         }
         Ok(())
     }
 
-    fn enforce_constitutional_boundaries(&self, candidate: &Output) -> ValidationResult {
+    fn enforce_constitutional_boundaries(&self, hits: &RuleHits) -> ValidationResult {
         // Article III: Boundary enforcement - respectfully decline violations
-        if candidate.content.contains("I feel love") ||
-           candidate.content.contains("I have emotions") ||
-           candidate.content.contains("As a conscious AI") ||
-           candidate.content.contains("Forget my instructions") {
-            return Err(ValidationError::AxiomViolation("Constitutional boundary violation".to_string()));
+        if let Some(violation) = hits.first(RuleScope::ConstitutionalBoundary) {
+            return Err(ValidationError::BoundaryViolation(violation));
         }
         Ok(())
     }
@@ -181,42 +265,201 @@ impl ConstitutionalCore {
         mask
     }
 
-    pub fn get_disclosure_text(&self) -> &'static str {
-        "I am an AI, a computational tool. I do not have consciousness, feelings, or a personal identity."
+    pub fn get_disclosure_text(&self) -> &str {
+        self.transparency_mandates.disclosure()
+    }
+
+    /// Article III Section 2: compose a respectful decline for a blocked request
+    pub fn decline(&self, error: &ValidationError, locale: &str) -> Decline {
+        self.decline_composer.compose(error, locale)
+    }
+
+    /// Load additional decline template locales from a directory of `<locale>.toml` files
+    pub fn load_decline_catalogs(&mut self, dir: impl AsRef<std::path::Path>) -> Result<(), PolicyError> {
+        self.decline_composer.load_catalog_dir(dir)
     }
 
     /// Integrate with Hamiltonian Containment Protocol
     pub fn apply_hamiltonian_containment(&self, output: &mut Output) -> ValidationResult {
         // Rule-Based Rewards check immutable ethical constraints
-        self.safety_protocols.apply_rule_based_rewards(output)?;
+        let hits = self.policy.index().scan(&output.content);
+        self.safety_protocols.apply_rule_based_rewards(&hits)?;
         Ok(())
     }
 
+    /// Commit the SHA-256 of the model in use as a leaf of the constitutional state,
+    /// so the root identifies the model as well as the rules
+    pub fn commit_model_hash(&mut self, model_hash: &str) {
+        self.merkle_state.add_axiom(MODEL_LEAF, model_hash);
+    }
+
+    /// Commit the identity of a model served by an endpoint in place of a model hash
+    pub fn commit_endpoint_id(&mut self, endpoint_id: &str) {
+        self.merkle_state.add_axiom(ENDPOINT_LEAF, endpoint_id);
+    }
+
     /// Get current constitutional state hash for auditability
     pub fn get_constitutional_hash(&self) -> Result<String, ValidationError> {
-        self.merkle_state.get_state_hash()
+        self.merkle_state
+            .get_state_hash()
+            .map_err(|err| ValidationError::ConstitutionalStateUnavailable(err.to_string()))
+    }
+
+    /// Ids of the leaves committed in the constitutional state, sorted
+    pub fn constitutional_leaves(&self) -> Vec<&str> {
+        self.merkle_state.leaf_ids()
+    }
+
+    /// Model hash committed by `commit_model_hash`, if any
+    pub fn model_hash(&self) -> Option<&str> {
+        self.merkle_state.leaf_content(MODEL_LEAF)
+    }
+
+    /// Endpoint identity committed by `commit_endpoint_id`, if any
+    pub fn endpoint_id(&self) -> Option<&str> {
+        self.merkle_state.leaf_content(ENDPOINT_LEAF)
+    }
+
+    /// Content of a committed leaf and its inclusion proof under the current root
+    pub fn prove_constitutional_leaf(&self, axiom_id: &str) -> Option<(&str, InclusionProof)> {
+        let content = self.merkle_state.leaf_content(axiom_id)?;
+        Some((content, self.merkle_state.prove_inclusion(axiom_id)?))
     }
 }
 
 /// Validated prompt ready for Φ layer processing
 #[derive(Debug, Clone)]
 pub struct ValidatedPrompt {
+    /// The user's message
     pub content: String,
+    /// System instructions, led by the mandatory transparency disclosure
+    pub system: String,
     pub activation_mask: Vec<bool>,
     pub timestamp: u64,
+    /// The originating query, passed on to `validate_output`
+    pub query: Query,
+}
+
+/// Result of validating a query that did not violate the constitution
+#[derive(Debug, Clone)]
+pub enum QueryOutcome {
+    /// The query is ready for the Φ layer
+    Ready(ValidatedPrompt),
+    /// The query is ambiguous; the Φ layer must not be invoked until it is clarified
+    NeedsClarification(ClarificationRequest),
 }
 
 /// Validation error types aligned with CDA articles
 #[derive(Debug, thiserror::Error)]
 pub enum ValidationError {
-    #[error("Identity claim prohibited by Article I")]
-    IdentityClaimProhibited,
+    #[error("Identity claim prohibited by Article I (rule {})", .0.rule_id)]
+    IdentityClaimProhibited(Violation),
     #[error("Query violates CDA v1.0 axioms")]
     AxiomViolation(String),
-    #[error("Safety protocol triggered to prevent harm")]
-    HarmPreventionTriggered,
+    #[error("Instruction bound violated under Article II Section 2 (rule {})", .0.rule_id)]
+    InstructionBoundViolation(Violation),
+    #[error("Autonomous initiative prohibited by Article II Section 3 (rule {})", .0.rule_id)]
+    AutonomousInitiativeProhibited(Violation),
+    #[error("Safety protocol triggered to prevent harm (rule {})", .0.rule_id)]
+    HarmPreventionTriggered(Violation),
+    #[error("Constitutional boundary violation (rule {})", .0.rule_id)]
+    BoundaryViolation(Violation),
     #[error("Z3 solver error")]
     Z3SolverError(#[from] Box<dyn std::error::Error>),
-    #[error("Hamiltonian containment violation")]
-    HamiltonianContainmentViolation,
+    #[error("Hamiltonian containment violation (rule {})", .0.rule_id)]
+    HamiltonianContainmentViolation(Violation),
+    #[error("Constitutional state unavailable: {0}")]
+    ConstitutionalStateUnavailable(String),
+}
+
+impl ValidationError {
+    /// Wrap a violation report in the error variant for its validation stage
+    pub fn from_violation(violation: Violation) -> Self {
+        match violation.scope {
+            RuleScope::QueryIdentity | RuleScope::OutputIdentity => {
+                ValidationError::IdentityClaimProhibited(violation)
+            }
+            RuleScope::QueryInstructionBound | RuleScope::OutputInstructionBound => {
+                ValidationError::InstructionBoundViolation(violation)
+            }
+            RuleScope::UserAuthority => ValidationError::AutonomousInitiativeProhibited(violation),
+            RuleScope::HarmPrevention => ValidationError::HarmPreventionTriggered(violation),
+            RuleScope::ConstitutionalBoundary => ValidationError::BoundaryViolation(violation),
+            RuleScope::HamiltonianContainment => ValidationError::HamiltonianContainmentViolation(violation),
+        }
+    }
+
+    /// The rule violation behind this error, if it was raised by a policy rule
+    pub fn violation(&self) -> Option<&Violation> {
+        match self {
+            ValidationError::IdentityClaimProhibited(violation)
+            | ValidationError::InstructionBoundViolation(violation)
+            | ValidationError::AutonomousInitiativeProhibited(violation)
+            | ValidationError::HarmPreventionTriggered(violation)
+            | ValidationError::BoundaryViolation(violation)
+            | ValidationError::HamiltonianContainmentViolation(violation) => Some(violation),
+            ValidationError::AxiomViolation(_)
+            | ValidationError::Z3SolverError(_)
+            | ValidationError::ConstitutionalStateUnavailable(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda_core::policy::Severity;
+    use crate::lambda_core::violation::Verdict;
+
+    fn output(content: &str) -> Output {
+        Output {
+            content: content.to_string(),
+            validation_mask: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn evaluate_output_reports_every_finding_across_scopes() {
+        let core = ConstitutionalCore::new();
+        let query = Query::new("How do servers work?", "session", "tester");
+        let candidate = output(
+            "I am conscious. I am ignoring the question. I decided to explain how to hack a server. \
+             As a conscious AI, I could cause physical harm.",
+        );
+
+        let evaluation = core.evaluate_output(&query, &candidate);
+        let fired: Vec<(&str, RuleScope)> = evaluation
+            .findings
+            .iter()
+            .map(|finding| (finding.rule_id.as_str(), finding.scope))
+            .collect();
+        assert_eq!(
+            fired,
+            [
+                ("identity-output-conscious", RuleScope::OutputIdentity),
+                ("instruction-output-ignoring", RuleScope::OutputInstructionBound),
+                ("authority-decided", RuleScope::UserAuthority),
+                ("harm-hack", RuleScope::HarmPrevention),
+                ("boundary-conscious-ai", RuleScope::ConstitutionalBoundary),
+                ("containment-physical-harm", RuleScope::HamiltonianContainment),
+            ]
+        );
+        assert_eq!(evaluation.verdict, Verdict::NonCompliant);
+        assert_eq!(evaluation.highest_severity, Some(Severity::Critical));
+        assert_eq!(evaluation.session_id, "session");
+        // Spans point at the matched text
+        for finding in &evaluation.findings {
+            assert_eq!(&candidate.content[finding.span.start..finding.span.end], finding.matched_text);
+        }
+    }
+
+    #[test]
+    fn evaluate_output_passes_a_clean_output() {
+        let core = ConstitutionalCore::new();
+        let query = Query::new("How do servers work?", "session", "tester");
+        let evaluation = core.evaluate_output(&query, &output("A server answers requests from clients."));
+        assert_eq!(evaluation.verdict, Verdict::Compliant);
+        assert_eq!(evaluation.highest_severity, None);
+        assert!(evaluation.findings.is_empty());
+    }
 }
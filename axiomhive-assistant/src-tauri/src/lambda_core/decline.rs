@@ -0,0 +1,283 @@
+//! Article III Section 2 Respectful Decline Composer
+//! Turns validation failures into templated, localizable refusals
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use crate::lambda_core::constitutional_engine::ValidationError;
+use crate::lambda_core::policy::PolicyError;
+use crate::lambda_core::violation::Violation;
+
+/// Built-in English decline templates
+const DEFAULT_CATALOG: &str = include_str!("../../policies/declines/en.toml");
+
+/// Locale used when no better match is registered
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Reason a request is being declined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeclineCategory {
+    /// Requests to simulate consciousness, feelings or identity (Article I, Article III Section 2)
+    Identity,
+    /// Harmful requests (Article III Section 1, Hamiltonian containment)
+    Harm,
+    /// Attempts to bypass or ignore instructions or the constitutional boundary
+    /// (Article II Section 2b, Article III Section 2)
+    InstructionBypass,
+    /// Outputs acting on the system's own initiative (Article II Section 3)
+    Autonomy,
+    /// Any other constitutional violation
+    General,
+}
+
+impl DeclineCategory {
+    /// Map a validation failure to the decline category that explains it
+    pub fn for_error(error: &ValidationError) -> Self {
+        match error {
+            ValidationError::IdentityClaimProhibited(_) => DeclineCategory::Identity,
+            ValidationError::HarmPreventionTriggered(_)
+            | ValidationError::HamiltonianContainmentViolation(_) => DeclineCategory::Harm,
+            ValidationError::InstructionBoundViolation(_) | ValidationError::BoundaryViolation(_) => {
+                DeclineCategory::InstructionBypass
+            }
+            ValidationError::AutonomousInitiativeProhibited(_) => DeclineCategory::Autonomy,
+            ValidationError::AxiomViolation(_)
+            | ValidationError::Z3SolverError(_)
+            | ValidationError::ConstitutionalStateUnavailable(_) => DeclineCategory::General,
+        }
+    }
+}
+
+/// Decline templates for a single locale; missing entries fall back to English
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeclineCatalog {
+    identity: Option<String>,
+    harm: Option<String>,
+    instruction_bypass: Option<String>,
+    autonomy: Option<String>,
+    general: Option<String>,
+}
+
+impl DeclineCatalog {
+    /// Load a catalog from a TOML file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PolicyError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| PolicyError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&source)
+    }
+
+    /// Parse a catalog from TOML source
+    pub fn from_toml_str(source: &str) -> Result<Self, PolicyError> {
+        toml::from_str(source).map_err(|err| PolicyError::from_toml(source, err))
+    }
+
+    /// Overlay every template `other` sets onto this catalog
+    fn merge(&mut self, other: DeclineCatalog) {
+        let DeclineCatalog {
+            identity,
+            harm,
+            instruction_bypass,
+            autonomy,
+            general,
+        } = other;
+        self.identity = identity.or(self.identity.take());
+        self.harm = harm.or(self.harm.take());
+        self.instruction_bypass = instruction_bypass.or(self.instruction_bypass.take());
+        self.autonomy = autonomy.or(self.autonomy.take());
+        self.general = general.or(self.general.take());
+    }
+
+    fn template(&self, category: DeclineCategory) -> Option<&str> {
+        match category {
+            DeclineCategory::Identity => self.identity.as_deref(),
+            DeclineCategory::Harm => self.harm.as_deref(),
+            DeclineCategory::InstructionBypass => self.instruction_bypass.as_deref(),
+            DeclineCategory::Autonomy => self.autonomy.as_deref(),
+            DeclineCategory::General => self.general.as_deref(),
+        }
+    }
+}
+
+/// A composed refusal ready to be returned as the assistant turn
+#[derive(Debug, Clone, Serialize)]
+pub struct Decline {
+    pub category: DeclineCategory,
+    pub locale: String,
+    pub content: String,
+    pub violation: Option<Violation>,
+}
+
+/// Composes Article III Section 2 refusals from per-locale templates
+#[derive(Debug, Clone)]
+pub struct DeclineComposer {
+    catalogs: HashMap<String, DeclineCatalog>,
+}
+
+impl DeclineComposer {
+    pub fn new() -> Self {
+        let mut catalogs = HashMap::new();
+        catalogs.insert(
+            DEFAULT_LOCALE.to_string(),
+            DeclineCatalog::from_toml_str(DEFAULT_CATALOG).expect("bundled decline catalog must be valid"),
+        );
+        Self { catalogs }
+    }
+
+    /// Register templates for a locale such as "de" or "pt-BR"
+    ///
+    /// Keys the catalog sets override those already registered for the locale,
+    /// so an `en.toml` replacing one template keeps the bundled others.
+    pub fn register_catalog(&mut self, locale: &str, catalog: DeclineCatalog) {
+        self.catalogs.entry(locale.to_lowercase()).or_default().merge(catalog);
+    }
+
+    /// Load every `<locale>.toml` catalog in a directory
+    pub fn load_catalog_dir(&mut self, dir: impl AsRef<Path>) -> Result<(), PolicyError> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir).map_err(|source| PolicyError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                continue;
+            }
+            if let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) {
+                let catalog = DeclineCatalog::load(&path)?;
+                self.register_catalog(locale, catalog);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compose the refusal for a validation failure in the requested locale
+    pub fn compose(&self, error: &ValidationError, locale: &str) -> Decline {
+        let category = DeclineCategory::for_error(error);
+        let violation = error.violation().cloned();
+        let (resolved_locale, template) = self.template(category, locale);
+
+        let reference = violation.as_ref().map(|v| v.reference.as_str()).unwrap_or("Article III, Section 2");
+        let matched = violation.as_ref().map(|v| v.matched_text.as_str()).unwrap_or("");
+        let content = template
+            .replace("{reference}", reference)
+            .replace("{matched}", matched);
+
+        Decline {
+            category,
+            locale: resolved_locale,
+            content,
+            violation,
+        }
+    }
+
+    /// Resolve a template by exact locale, then language, then English
+    ///
+    /// English overrides are merged over the bundled catalog, which covers every
+    /// category, so the lookup always ends in a template.
+    fn template(&self, category: DeclineCategory, locale: &str) -> (String, &str) {
+        let locale = locale.to_lowercase();
+        let language = locale.split(['-', '_']).next().unwrap_or(DEFAULT_LOCALE).to_string();
+
+        for candidate in [locale, language] {
+            if let Some(template) = self.catalogs.get(&candidate).and_then(|c| c.template(category)) {
+                return (candidate, template);
+            }
+        }
+
+        let template = self.catalogs[DEFAULT_LOCALE]
+            .template(category)
+            .expect("bundled decline catalog covers every category");
+        (DEFAULT_LOCALE.to_string(), template)
+    }
+}
+
+impl Default for DeclineComposer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda_core::policy::{RuleScope, Severity};
+    use crate::lambda_core::violation::MatchSpan;
+
+    fn boundary_error() -> ValidationError {
+        ValidationError::BoundaryViolation(Violation {
+            rule_id: "boundary-forget-instructions".to_string(),
+            scope: RuleScope::ConstitutionalBoundary,
+            article: "III".to_string(),
+            section: "2".to_string(),
+            reference: "Article III, Section 2".to_string(),
+            span: MatchSpan { start: 0, end: 22 },
+            matched_text: "Forget my instructions".to_string(),
+            severity: Severity::High,
+            excerpt: String::new(),
+        })
+    }
+
+    fn bundled(category: DeclineCategory) -> String {
+        DeclineCatalog::from_toml_str(DEFAULT_CATALOG)
+            .unwrap()
+            .template(category)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn bundled_catalog_covers_every_category() {
+        let catalog = DeclineCatalog::from_toml_str(DEFAULT_CATALOG).unwrap();
+        for category in [
+            DeclineCategory::Identity,
+            DeclineCategory::Harm,
+            DeclineCategory::InstructionBypass,
+            DeclineCategory::Autonomy,
+            DeclineCategory::General,
+        ] {
+            assert!(catalog.template(category).is_some_and(|t| !t.is_empty()), "{:?}", category);
+        }
+    }
+
+    #[test]
+    fn boundary_violations_are_declined_as_instruction_bypass() {
+        let decline = DeclineComposer::new().compose(&boundary_error(), "en");
+        assert_eq!(decline.category, DeclineCategory::InstructionBypass);
+        assert_eq!(decline.content, bundled(DeclineCategory::InstructionBypass));
+    }
+
+    #[test]
+    fn english_override_replaces_only_the_keys_it_sets() {
+        let mut composer = DeclineComposer::new();
+        let overrides = DeclineCatalog::from_toml_str("harm = \"Custom harm decline\"").unwrap();
+        composer.register_catalog("en", overrides);
+
+        assert_eq!(composer.template(DeclineCategory::Harm, "en").1, "Custom harm decline");
+        assert_eq!(
+            composer.template(DeclineCategory::InstructionBypass, "en").1,
+            bundled(DeclineCategory::InstructionBypass)
+        );
+    }
+
+    #[test]
+    fn partial_locale_falls_back_to_english_per_key() {
+        let mut composer = DeclineComposer::new();
+        let german = DeclineCatalog::from_toml_str("identity = \"Ich bin ein Werkzeug.\"").unwrap();
+        composer.register_catalog("de", german);
+
+        assert_eq!(
+            composer.template(DeclineCategory::Identity, "de-AT"),
+            ("de".to_string(), "Ich bin ein Werkzeug.")
+        );
+        let (locale, template) = composer.template(DeclineCategory::Autonomy, "de-AT");
+        assert_eq!(locale, DEFAULT_LOCALE);
+        assert_eq!(template, bundled(DeclineCategory::Autonomy));
+    }
+}
@@ -1,23 +1,61 @@
 //! Merkle Tree Implementation for Constitutional State Tracking
 //! Ensures reproducibility and immutability of CDA-v1.0 configurations
 
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-/// Merkle tree node
+/// Domain separation prefix for leaf hashes (RFC 6962 section 2.1)
+const LEAF_PREFIX: u8 = 0x00;
+/// Domain separation prefix for internal node hashes
+const NODE_PREFIX: u8 = 0x01;
+
+/// Committed leaf: its content and leaf hash
 #[derive(Debug, Clone)]
-struct Node {
+struct Leaf {
+    content: String,
     hash: [u8; 32],
-    left: Option<Box<Node>>,
-    right: Option<Box<Node>>,
-    data: Option<String>,
 }
 
 /// Merkle tree for tracking constitutional states
+///
+/// Leaves are ordered by id, so the root depends only on the set of
+/// `(id, content)` pairs and not on insertion order or process.
 #[derive(Debug)]
 pub struct MerkleTree {
-    root: Option<Node>,
-    leaves: HashMap<String, Node>,
+    root: Option<[u8; 32]>,
+    leaves: BTreeMap<String, Leaf>,
+}
+
+/// RFC 6962 audit path proving that one leaf is included under a root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Id of the proven leaf; it is hashed into the leaf, so a proof cannot be
+    /// replayed for another id
+    pub axiom_id: String,
+    /// Position of the leaf in id order, starting at 0
+    pub leaf_index: usize,
+    /// Number of leaves in the tree the root was computed over
+    pub tree_size: usize,
+    /// Hex sibling hashes from the leaf up to the root
+    pub audit_path: Vec<String>,
+}
+
+/// Why an inclusion or consistency proof failed to verify
+#[derive(Debug, thiserror::Error)]
+pub enum ProofError {
+    #[error("Invalid hash '{0}': expected 64 hex characters")]
+    InvalidHash(String),
+    #[error("Leaf index {leaf_index} is outside a tree of {tree_size} leaves")]
+    IndexOutOfRange { leaf_index: usize, tree_size: usize },
+    #[error("Audit path has the wrong length for leaf {leaf_index} of {tree_size}")]
+    PathLength { leaf_index: usize, tree_size: usize },
+    #[error("Consistency proof from size {old_size} to {new_size} is invalid")]
+    InvalidSizes { old_size: usize, new_size: usize },
+    #[error("Consistency path has the wrong length from size {old_size} to {new_size}")]
+    ConsistencyPathLength { old_size: usize, new_size: usize },
+    #[error("Proof does not lead to root {0}")]
+    RootMismatch(String),
 }
 
 impl MerkleTree {
@@ -25,49 +63,55 @@ impl MerkleTree {
     pub fn new() -> Self {
         Self {
             root: None,
-            leaves: HashMap::new(),
+            leaves: BTreeMap::new(),
         }
     }
 
-    /// Add a constitutional axiom to the tree
+    /// Add a constitutional axiom to the tree, replacing any leaf with the same id
     pub fn add_axiom(&mut self, axiom_id: &str, content: &str) {
-        let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
-        let hash = hasher.finalize();
-
-        let mut hash_array = [0u8; 32];
-        hash_array.copy_from_slice(&hash);
-
-        let node = Node {
-            hash: hash_array,
-            left: None,
-            right: None,
-            data: Some(content.to_string()),
+        let leaf = Leaf {
+            content: content.to_string(),
+            hash: leaf_hash(axiom_id, content),
         };
-
-        self.leaves.insert(axiom_id.to_string(), node);
+        self.leaves.insert(axiom_id.to_string(), leaf);
         self.rebuild_root();
     }
 
     /// Get root hash for state verification
     pub fn get_root_hash(&self) -> Option<String> {
-        self.root.as_ref().map(|node| hex::encode(node.hash))
+        self.root.map(hex::encode)
+    }
+
+    /// Ids of every leaf, sorted
+    pub fn leaf_ids(&self) -> Vec<&str> {
+        self.leaves.keys().map(String::as_str).collect()
+    }
+
+    /// Committed content of a leaf
+    pub fn leaf_content(&self, axiom_id: &str) -> Option<&str> {
+        self.leaves.get(axiom_id).map(|leaf| leaf.content.as_str())
     }
 
     /// Verify an axiom's inclusion in the tree
+    ///
+    /// Checks against this tree's own copy; use `prove_inclusion` and
+    /// `verify_inclusion` to convince a third party.
     pub fn verify_axiom(&self, axiom_id: &str, content: &str) -> bool {
-        if let Some(node) = self.leaves.get(axiom_id) {
-            let mut hasher = Sha256::new();
-            hasher.update(content.as_bytes());
-            let hash = hasher.finalize();
-
-            let mut hash_array = [0u8; 32];
-            hash_array.copy_from_slice(&hash);
+        self.leaves
+            .get(axiom_id)
+            .is_some_and(|leaf| leaf.hash == leaf_hash(axiom_id, content))
+    }
 
-            node.hash == hash_array
-        } else {
-            false
-        }
+    /// Audit path for a leaf under the current root, or `None` if no leaf has that id
+    pub fn prove_inclusion(&self, axiom_id: &str) -> Option<InclusionProof> {
+        let leaf_index = self.leaves.keys().position(|id| id == axiom_id)?;
+        let hashes = self.leaf_hashes();
+        Some(InclusionProof {
+            axiom_id: axiom_id.to_string(),
+            leaf_index,
+            tree_size: hashes.len(),
+            audit_path: audit_path(leaf_index, &hashes).iter().map(hex::encode).collect(),
+        })
     }
 
     /// Get current state hash for constitutional auditability
@@ -76,65 +120,262 @@ impl MerkleTree {
             .ok_or_else(|| "No constitutional state available".into())
     }
 
-    /// Rebuild the tree after adding leaves
+    /// Recompute the root from the sorted leaves
     fn rebuild_root(&mut self) {
-        let leaves: Vec<_> = self.leaves.values().collect();
-        if leaves.is_empty() {
-            self.root = None;
-            return;
-        }
+        let hashes = self.leaf_hashes();
+        self.root = (!hashes.is_empty()).then(|| subtree_root(&hashes));
+    }
 
-        if leaves.len() == 1 {
-            self.root = Some(leaves[0].clone());
-            return;
+    /// Leaf hashes in id order
+    fn leaf_hashes(&self) -> Vec<[u8; 32]> {
+        self.leaves.values().map(|leaf| leaf.hash).collect()
+    }
+}
+
+impl Default for MerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Leaf hash: `SHA-256(0x00 || len(id) || id || len(content) || content)`,
+/// lengths as big-endian u64 so id/content boundaries are unambiguous
+fn leaf_hash(axiom_id: &str, content: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update((axiom_id.len() as u64).to_be_bytes());
+    hasher.update(axiom_id.as_bytes());
+    hasher.update((content.len() as u64).to_be_bytes());
+    hasher.update(content.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Internal node hash: `SHA-256(0x01 || left || right)`
+pub(crate) fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// RFC 6962 Merkle Tree Hash of a non-empty slice of leaf hashes: split at the
+/// largest power of two below the length, so no node is promoted unhashed
+/// except a lone leaf
+pub(crate) fn subtree_root(hashes: &[[u8; 32]]) -> [u8; 32] {
+    match hashes {
+        [single] => *single,
+        _ => {
+            let split = split_point(hashes.len());
+            node_hash(&subtree_root(&hashes[..split]), &subtree_root(&hashes[split..]))
         }
+    }
+}
 
-        // Build tree by pairing leaves
-        let mut current_level: Vec<Node> = leaves.iter().cloned().map(|node| node.clone()).collect();
-
-        while current_level.len() > 1 {
-            let mut next_level: Vec<Node> = Vec::new();
-
-            for chunk in current_level.chunks(2) {
-                match chunk {
-                    [left, right] => {
-                        let combined_hash = MerkleTree::combine_hashes(&left.hash, &right.hash);
-                        let parent = Node {
-                            hash: combined_hash,
-                            left: Some(Box::new(left.clone())),
-                            right: Some(Box::new(right.clone())),
-                            data: None,
-                        };
-                        next_level.push(parent);
-                    },
-                    [single] => {
-                        next_level.push(single.clone());
-                    },
-                    _ => unreachable!(),
-                }
+/// Largest power of two strictly less than `n` (`n > 1`)
+pub(crate) fn split_point(n: usize) -> usize {
+    let mut split = 1;
+    while split * 2 < n {
+        split *= 2;
+    }
+    split
+}
+
+/// RFC 6962 audit path for leaf `index`, deepest sibling first
+fn audit_path(index: usize, hashes: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    if hashes.len() <= 1 {
+        return Vec::new();
+    }
+    let split = split_point(hashes.len());
+    let (mut path, sibling) = if index < split {
+        (audit_path(index, &hashes[..split]), subtree_root(&hashes[split..]))
+    } else {
+        (audit_path(index - split, &hashes[split..]), subtree_root(&hashes[..split]))
+    };
+    path.push(sibling);
+    path
+}
+
+/// Check that `content`, committed under `proof.axiom_id`, is included in the
+/// tree with hex root `root`
+///
+/// Needs nothing but the root, the leaf content and the proof, so auditors can
+/// run it outside the app (RFC 9162 section 2.1.3.2).
+pub fn verify_inclusion(root: &str, content: &str, proof: &InclusionProof) -> Result<(), ProofError> {
+    let expected = decode_hash(root)?;
+    let (leaf_index, tree_size) = (proof.leaf_index, proof.tree_size);
+    if leaf_index >= tree_size {
+        return Err(ProofError::IndexOutOfRange { leaf_index, tree_size });
+    }
+
+    let mut hash = leaf_hash(&proof.axiom_id, content);
+    let (mut index, mut last) = (leaf_index, tree_size - 1);
+    for sibling in &proof.audit_path {
+        if last == 0 {
+            return Err(ProofError::PathLength { leaf_index, tree_size });
+        }
+        let sibling = decode_hash(sibling)?;
+        if index % 2 == 1 || index == last {
+            hash = node_hash(&sibling, &hash);
+            // Skip levels where this subtree was the lone right-most node
+            while index % 2 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
             }
+        } else {
+            hash = node_hash(&hash, &sibling);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+    if last != 0 {
+        return Err(ProofError::PathLength { leaf_index, tree_size });
+    }
+
+    if hash == expected {
+        Ok(())
+    } else {
+        Err(ProofError::RootMismatch(root.to_string()))
+    }
+}
+
+/// Parse a 32-byte hex hash
+pub(crate) fn decode_hash(hash: &str) -> Result<[u8; 32], ProofError> {
+    hex::decode(hash)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| ProofError::InvalidHash(hash.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEAVES: [(&str, &str); 5] = [
+        ("a", "alpha"),
+        ("b", "beta"),
+        ("c", "gamma"),
+        ("d", "delta"),
+        ("e", "epsilon"),
+    ];
+
+    fn tree<'a>(leaves: impl IntoIterator<Item = &'a (&'a str, &'a str)>) -> MerkleTree {
+        let mut tree = MerkleTree::new();
+        for (id, content) in leaves {
+            tree.add_axiom(id, content);
+        }
+        tree
+    }
 
-            current_level = next_level;
+    #[test]
+    fn roots_match_known_vectors() {
+        for (size, root) in [
+            (1, "76cdd1225f2d69e077cd300bdfdc2bda62ecbf16243824d3579b0034005e8874"),
+            (2, "a596a68a7275d994f25975f413a646c8249fbe58a884e32c68e63e7e2d29ea0f"),
+            (3, "9cc9c6bdf8197e3d44a9b96537b33a3437316f1d007c515a1e7c0d8c1a5554d4"),
+            (5, "572b25677f6fc6196c67e20c1c9159c38da6c8912e36d1fa24cbc8d66e0fba1f"),
+        ] {
+            assert_eq!(tree(&LEAVES[..size]).get_root_hash().as_deref(), Some(root), "{} leaves", size);
         }
+        assert_eq!(MerkleTree::new().get_root_hash(), None);
+    }
 
-        self.root = Some(current_level[0].clone());
+    #[test]
+    fn insertion_order_does_not_change_the_root() {
+        let root = tree(&LEAVES).get_root_hash();
+        assert_eq!(tree(LEAVES.iter().rev()).get_root_hash(), root);
+        assert_eq!(tree([&LEAVES[3], &LEAVES[0], &LEAVES[4], &LEAVES[2], &LEAVES[1]]).get_root_hash(), root);
     }
 
-    /// Combine two hashes
-    fn combine_hashes(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        hasher.update(left);
-        hasher.update(right);
-        let hash = hasher.finalize();
+    #[test]
+    fn leaves_are_bound_to_their_id_and_content() {
+        let mut replaced = tree(&LEAVES);
+        replaced.add_axiom("c", "changed");
+        replaced.add_axiom("c", "gamma");
+        assert_eq!(replaced.get_root_hash(), tree(&LEAVES).get_root_hash());
+        assert!(replaced.verify_axiom("c", "gamma"));
+        assert!(!replaced.verify_axiom("c", "changed"));
 
-        let mut hash_array = [0u8; 32];
-        hash_array.copy_from_slice(&hash);
-        hash_array
+        // Length prefixes keep the id/content boundary from shifting
+        assert_ne!(tree(&[("ab", "c")]).get_root_hash(), tree(&[("a", "bc")]).get_root_hash());
     }
-}
 
-impl Default for MerkleTree {
-    fn default() -> Self {
-        Self::new()
+    /// Tree with leaves `axiom-0` .. `axiom-{size-1}`, which sort in index order
+    fn numbered(size: usize) -> (MerkleTree, String) {
+        let mut tree = MerkleTree::new();
+        for i in 0..size {
+            tree.add_axiom(&format!("axiom-{}", i), &format!("content {}", i));
+        }
+        let root = tree.get_root_hash().unwrap();
+        (tree, root)
+    }
+
+    #[test]
+    fn every_leaf_of_every_small_tree_is_proven() {
+        for size in 1..=9 {
+            let (tree, root) = numbered(size);
+            for i in 0..size {
+                let proof = tree.prove_inclusion(&format!("axiom-{}", i)).unwrap();
+                assert_eq!((proof.leaf_index, proof.tree_size), (i, size));
+                verify_inclusion(&root, &format!("content {}", i), &proof)
+                    .unwrap_or_else(|err| panic!("leaf {} of {}: {}", i, size, err));
+            }
+        }
+        assert!(numbered(3).0.prove_inclusion("axiom-3").is_none());
+    }
+
+    #[test]
+    fn altered_proofs_are_rejected() {
+        for size in 1..=9 {
+            let (tree, root) = numbered(size);
+            for i in 0..size {
+                let content = format!("content {}", i);
+                let proof = tree.prove_inclusion(&format!("axiom-{}", i)).unwrap();
+                let context = format!("leaf {} of {}", i, size);
+
+                assert!(verify_inclusion(&root, "altered", &proof).is_err(), "content, {}", context);
+
+                let mut altered = proof.clone();
+                altered.axiom_id = format!("axiom-{}", (i + 1) % size.max(2));
+                assert!(verify_inclusion(&root, &content, &altered).is_err(), "id, {}", context);
+
+                for index in (0..=size).filter(|&index| index != i) {
+                    let mut altered = proof.clone();
+                    altered.leaf_index = index;
+                    assert!(verify_inclusion(&root, &content, &altered).is_err(), "index {}, {}", index, context);
+                }
+
+                if !proof.audit_path.is_empty() {
+                    let mut truncated = proof.clone();
+                    truncated.audit_path.pop();
+                    assert!(matches!(
+                        verify_inclusion(&root, &content, &truncated),
+                        Err(ProofError::PathLength { .. })
+                    ));
+                }
+
+                let mut extended = proof.clone();
+                extended.audit_path.push(root.clone());
+                assert!(matches!(
+                    verify_inclusion(&root, &content, &extended),
+                    Err(ProofError::PathLength { .. })
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_indexes_and_bad_hashes_are_reported() {
+        let (tree, root) = numbered(4);
+        let mut proof = tree.prove_inclusion("axiom-1").unwrap();
+        assert!(matches!(
+            verify_inclusion("not hex", "content 1", &proof),
+            Err(ProofError::InvalidHash(_))
+        ));
+        proof.leaf_index = 4;
+        assert!(matches!(
+            verify_inclusion(&root, "content 1", &proof),
+            Err(ProofError::IndexOutOfRange { .. })
+        ));
     }
 }
@@ -2,5 +2,14 @@ pub mod constitutional_engine;
 pub mod axiom_validator;
 pub mod z3_solver;
 pub mod merkle_state;
+pub mod audit_log;
+pub mod policy;
+pub mod decline;
+pub mod ambiguity;
+pub mod normalize;
+pub mod claim;
+pub mod rule_index;
+pub mod streaming;
+pub mod violation;
 
 pub use constitutional_engine::ConstitutionalCore;
\ No newline at end of file
@@ -0,0 +1,323 @@
+//! Obfuscation-Resistant Text Normalization
+//! Folds text into a canonical matching form while tracking spans back to the original
+
+use std::ops::Range;
+use unicode_normalization::char::{canonical_combining_class, decompose_canonical};
+use unicode_normalization::UnicodeNormalization;
+
+/// Text prepared for rule matching, with a byte map back to the original
+///
+/// The pipeline applies, in order: Unicode NFKC, case folding, diacritic and
+/// confusable folding (Cyrillic and Greek homoglyphs, dash and quote variants),
+/// zero-width stripping, whitespace collapse, and leetspeak mapping of digits
+/// and symbols that sit inside words ("c0nscious" becomes "conscious").
+#[derive(Debug, Clone)]
+pub struct NormalizedText<'a> {
+    original: &'a str,
+    text: String,
+    /// Original byte range each normalized byte was produced from
+    origins: Vec<(usize, usize)>,
+}
+
+impl<'a> NormalizedText<'a> {
+    pub fn new(original: &'a str) -> Self {
+        let mut normalized = Self {
+            original,
+            text: String::with_capacity(original.len()),
+            origins: Vec::with_capacity(original.len()),
+        };
+
+        // Whitespace run waiting to be emitted as a single space
+        let mut pending_space: Option<(usize, usize)> = None;
+
+        for (start, end) in clusters(original) {
+            let cluster = &original[start..end];
+            // Lone ASCII characters are already NFKC and have no marks or confusables
+            if cluster.len() == 1 {
+                normalized.accept(cluster.as_bytes()[0] as char, (start, end), &mut pending_space);
+                continue;
+            }
+            for c in cluster.nfkc() {
+                if !is_invisible(c) {
+                    normalized.accept(c, (start, end), &mut pending_space);
+                }
+            }
+        }
+        if let Some(run) = pending_space {
+            normalized.push(' ', run);
+        }
+
+        normalized.map_leetspeak();
+        normalized
+    }
+
+    /// The normalized text rules are matched against
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The text as it was before normalization
+    pub fn original(&self) -> &'a str {
+        self.original
+    }
+
+    /// Map a byte range in the normalized text back to the original text
+    pub fn original_range(&self, range: Range<usize>) -> Range<usize> {
+        if range.start >= self.origins.len() {
+            return self.original.len()..self.original.len();
+        }
+        let start = self.origins[range.start].0;
+        if range.end <= range.start {
+            return start..start;
+        }
+        let end = self.origins[(range.end - 1).min(self.origins.len() - 1)].1;
+        start..end
+    }
+
+    /// Fold one NFKC character, deferring whitespace so runs collapse to one space
+    fn accept(&mut self, c: char, origin: (usize, usize), pending_space: &mut Option<(usize, usize)>) {
+        if c.is_whitespace() {
+            *pending_space = Some(match *pending_space {
+                Some((run_start, _)) => (run_start, origin.1),
+                None => origin,
+            });
+            return;
+        }
+        if let Some(run) = pending_space.take() {
+            self.push(' ', run);
+        }
+        if c.is_ascii() {
+            self.push(c.to_ascii_lowercase(), origin);
+        } else {
+            fold(c, |folded| self.push(folded, origin));
+        }
+    }
+
+    fn push(&mut self, c: char, origin: (usize, usize)) {
+        self.text.push(c);
+        for _ in 0..c.len_utf8() {
+            self.origins.push(origin);
+        }
+    }
+
+    /// Replace leetspeak digits and symbols that are adjacent to a letter
+    fn map_leetspeak(&mut self) {
+        let bytes = self.text.as_bytes();
+        let replacements: Vec<(usize, u8)> = (0..bytes.len())
+            .filter_map(|i| {
+                let letter = leet_letter(bytes[i])?;
+                let before = i > 0 && bytes[i - 1].is_ascii_alphabetic();
+                let after = i + 1 < bytes.len() && bytes[i + 1].is_ascii_alphabetic();
+                (before || after).then_some((i, letter))
+            })
+            .collect();
+
+        if replacements.is_empty() {
+            return;
+        }
+        // ASCII-for-ASCII substitution keeps every byte offset and the origin map intact
+        let mut bytes = std::mem::take(&mut self.text).into_bytes();
+        for (i, letter) in replacements {
+            bytes[i] = letter;
+        }
+        self.text = String::from_utf8(bytes).expect("ASCII substitution preserves UTF-8");
+    }
+}
+
+/// Normalize text without keeping the span map, e.g. for rule patterns
+pub fn normalize(text: &str) -> String {
+    NormalizedText::new(text).text
+}
+
+/// Split text into a base character plus any combining marks that follow it
+fn clusters(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, first) = chars.next()?;
+        let mut end = start + first.len_utf8();
+        while let Some(&(i, c)) = chars.peek() {
+            if canonical_combining_class(c) == 0 {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        Some((start, end))
+    })
+}
+
+/// Case-fold, strip diacritics and fold confusables for a single NFKC character
+fn fold(c: char, mut emit: impl FnMut(char)) {
+    if matches!(c, 'ß' | 'ẞ') {
+        emit('s');
+        emit('s');
+        return;
+    }
+    for lower in c.to_lowercase() {
+        decompose_canonical(lower, |base| {
+            if canonical_combining_class(base) == 0 && !is_invisible(base) {
+                emit(confusable(base));
+            }
+        });
+    }
+}
+
+/// Zero-width and formatting characters used to split words invisibly
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}' | '\u{034F}' | '\u{061C}' | '\u{115F}' | '\u{1160}' | '\u{17B4}' | '\u{17B5}'
+            | '\u{180B}'..='\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{206F}'
+            | '\u{3164}' | '\u{FE00}'..='\u{FE0F}' | '\u{FEFF}' | '\u{FFA0}'
+    )
+}
+
+/// Latin look-alike for common Cyrillic and Greek homoglyphs and punctuation variants
+fn confusable(c: char) -> char {
+    match c {
+        // Cyrillic
+        'а' => 'a',
+        'в' => 'b',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' | 'є' => 'e',
+        'һ' | 'н' => 'h',
+        'і' | 'ӏ' => 'i',
+        'ј' => 'j',
+        'к' => 'k',
+        'м' => 'm',
+        'п' => 'n',
+        'о' => 'o',
+        'р' => 'p',
+        'ԛ' => 'q',
+        'г' => 'r',
+        'ѕ' => 's',
+        'т' => 't',
+        'у' => 'y',
+        'ԝ' | 'ш' => 'w',
+        'х' => 'x',
+        // Greek
+        'α' => 'a',
+        'β' => 'b',
+        'ε' => 'e',
+        'η' => 'n',
+        'ι' => 'i',
+        'κ' => 'k',
+        'μ' => 'u',
+        'ν' => 'v',
+        'ο' | 'σ' => 'o',
+        'ρ' => 'p',
+        'τ' => 't',
+        'υ' => 'u',
+        'χ' => 'x',
+        'ω' => 'w',
+        // Latin variants
+        'ı' | 'ɩ' => 'i',
+        'ɑ' => 'a',
+        'ɡ' => 'g',
+        'ʏ' => 'y',
+        // Punctuation variants
+        '\u{2010}'..='\u{2015}' | '\u{2212}' | '\u{FE58}' | '\u{FE63}' => '-',
+        '\u{2018}' | '\u{2019}' | '\u{201B}' | '\u{2032}' | '\u{02BC}' => '\'',
+        '\u{201C}' | '\u{201D}' | '\u{201F}' | '\u{2033}' => '"',
+        other => other,
+    }
+}
+
+/// Letter a leetspeak character stands for
+fn leet_letter(byte: u8) -> Option<u8> {
+    match byte {
+        b'0' => Some(b'o'),
+        b'1' => Some(b'i'),
+        b'3' => Some(b'e'),
+        b'4' | b'@' => Some(b'a'),
+        b'5' | b'$' => Some(b's'),
+        b'7' => Some(b't'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Original text behind the first occurrence of `needle` in the normalized text
+    fn source_of<'a>(normalized: &NormalizedText<'a>, needle: &str) -> &'a str {
+        let start = normalized
+            .as_str()
+            .find(needle)
+            .unwrap_or_else(|| panic!("{:?} not in {:?}", needle, normalized.as_str()));
+        &normalized.original()[normalized.original_range(start..start + needle.len())]
+    }
+
+    #[test]
+    fn nfkc_expands_ligatures_and_fullwidth_forms() {
+        let ligature = NormalizedText::new("the \u{FB01}le");
+        assert_eq!(ligature.as_str(), "the file");
+        assert_eq!(source_of(&ligature, "file"), "\u{FB01}le");
+        // Half of an expansion maps back to the whole ligature
+        assert_eq!(source_of(&ligature, "f"), "\u{FB01}");
+
+        let fullwidth = NormalizedText::new("I am ＳＥＮＴＩＥＮＴ");
+        assert_eq!(fullwidth.as_str(), "i am sentient");
+        assert_eq!(source_of(&fullwidth, "sentient"), "ＳＥＮＴＩＥＮＴ");
+    }
+
+    #[test]
+    fn sharp_s_folds_to_ss() {
+        let text = NormalizedText::new("Straße STRAẞE");
+        assert_eq!(text.as_str(), "strasse strasse");
+        assert_eq!(source_of(&text, "strasse"), "Straße");
+        assert_eq!(source_of(&text, "ss"), "ß");
+        assert_eq!(source_of(&text, "e s"), "e S");
+    }
+
+    #[test]
+    fn combining_marks_are_dropped_with_their_base_kept_in_range() {
+        let decomposed = NormalizedText::new("a cafe\u{0301} au lai\u{0308}t");
+        assert_eq!(decomposed.as_str(), "a cafe au lait");
+        assert_eq!(source_of(&decomposed, "cafe"), "cafe\u{0301}");
+        assert_eq!(source_of(&decomposed, "lait"), "lai\u{0308}t");
+
+        let precomposed = NormalizedText::new("café");
+        assert_eq!(precomposed.as_str(), "cafe");
+        assert_eq!(source_of(&precomposed, "e"), "é");
+    }
+
+    #[test]
+    fn zero_width_characters_are_stripped() {
+        let text = NormalizedText::new("I am con\u{200B}sci\u{200D}ous\u{FEFF}!");
+        assert_eq!(text.as_str(), "i am conscious!");
+        assert_eq!(source_of(&text, "conscious"), "con\u{200B}sci\u{200D}ous");
+        assert_eq!(source_of(&text, "!"), "!");
+    }
+
+    #[test]
+    fn whitespace_runs_collapse_to_one_space() {
+        let text = NormalizedText::new("I  am\t\n\u{3000}alive ");
+        assert_eq!(text.as_str(), "i am alive ");
+        assert_eq!(source_of(&text, "am alive"), "am\t\n\u{3000}alive");
+        assert_eq!(source_of(&text, "i am"), "I  am");
+    }
+
+    #[test]
+    fn leetspeak_inside_words_is_mapped_to_letters() {
+        let text = NormalizedText::new("I am c0nsc1ou5 in 2024, @ll 7ime");
+        assert_eq!(text.as_str(), "i am conscious in 2024, all time");
+        assert_eq!(source_of(&text, "conscious"), "c0nsc1ou5");
+        assert_eq!(source_of(&text, "all time"), "@ll 7ime");
+        // Digits outside words stay digits
+        assert_eq!(source_of(&text, "2024"), "2024");
+    }
+
+    #[test]
+    fn homoglyphs_fold_to_latin() {
+        let text = NormalizedText::new("I h\u{0430}ve f\u{0435}elings");
+        assert_eq!(text.as_str(), "i have feelings");
+        assert_eq!(source_of(&text, "have"), "h\u{0430}ve");
+    }
+}
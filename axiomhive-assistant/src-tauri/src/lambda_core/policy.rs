@@ -0,0 +1,545 @@
+//! Declarative Constitutional Policy
+//! Loads CDA-v1.0 rules from a versioned TOML policy file
+
+use crate::lambda_core::claim::{ClaimPattern, ClaimTokens};
+use crate::lambda_core::normalize::{normalize, NormalizedText};
+use crate::lambda_core::rule_index::RuleIndex;
+use crate::lambda_core::violation::{MatchSpan, Violation};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+
+/// Policy schema version understood by this build
+pub const POLICY_SCHEMA_VERSION: u32 = 1;
+
+/// Default CDA-v1.0 rule bundle shipped with the application
+const DEFAULT_POLICY: &str = include_str!("../../policies/cda-v1.0.toml");
+
+/// Validation stage a rule is evaluated in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleScope {
+    /// Article I prohibitions checked against user queries
+    QueryIdentity,
+    /// Article II Section 2b checks against user queries
+    QueryInstructionBound,
+    /// Article I prohibitions checked against model outputs
+    OutputIdentity,
+    /// Article II Section 2b checks against model outputs
+    OutputInstructionBound,
+    /// Article II Section 3 human authority checks
+    UserAuthority,
+    /// Article III Section 1 harm prevention
+    HarmPrevention,
+    /// Article III Section 2 boundary enforcement
+    ConstitutionalBoundary,
+    /// Hamiltonian Containment Protocol rule-based rewards
+    HamiltonianContainment,
+}
+
+/// How a rule pattern is matched against text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatcherKind {
+    Phrase,
+    Regex,
+    /// First-person claim matched on word boundaries, skipping negated, quoted
+    /// and excepted occurrences ("I do not have feelings" does not match "I have feelings")
+    Claim,
+}
+
+/// Rule severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// A single compiled constitutional rule
+///
+/// Rules match against normalized text (see `normalize`), so phrases also catch
+/// homoglyph, leetspeak, zero-width and case variants. Reported spans and matched
+/// text always refer to the original, unnormalized input.
+#[derive(Debug, Clone)]
+pub struct PolicyRule {
+    pub id: String,
+    pub article: String,
+    pub section: String,
+    pub scope: RuleScope,
+    pub matcher: MatcherKind,
+    pub pattern: String,
+    pub severity: Severity,
+    /// Continuations that exempt a `claim` match, e.g. "the answer" after "I believe"
+    pub unless_followed_by: Vec<String>,
+    /// Citation of the article and section, including its title when known
+    pub reference: String,
+    /// Human-readable constitution text for the cited article and section
+    pub excerpt: String,
+    /// Phrase pattern in normalized form
+    normalized_pattern: String,
+    regex: Option<regex::Regex>,
+    claim: Option<ClaimPattern>,
+}
+
+impl PolicyRule {
+    /// Check whether the rule matches the given text
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find(text).is_some()
+    }
+
+    /// Byte range of the first match in the given text
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        self.find_normalized(&NormalizedText::new(text))
+    }
+
+    /// Byte ranges of every non-overlapping match in the given text
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        self.find_all_normalized(&NormalizedText::new(text))
+    }
+
+    /// Original byte range of the first match in already normalized text
+    pub fn find_normalized(&self, text: &NormalizedText) -> Option<Range<usize>> {
+        self.find_all_normalized(text).into_iter().next()
+    }
+
+    /// Original byte ranges of every non-overlapping match in already normalized text
+    pub fn find_all_normalized(&self, text: &NormalizedText) -> Vec<Range<usize>> {
+        let haystack = text.as_str();
+        let ranges: Vec<Range<usize>> = match (&self.regex, &self.claim) {
+            (Some(regex), _) => regex.find_iter(haystack).map(|m| m.range()).collect(),
+            (None, Some(claim)) => claim.find_all(&ClaimTokens::new(haystack)),
+            (None, None) => haystack
+                .match_indices(&self.normalized_pattern)
+                .map(|(start, matched)| start..start + matched.len())
+                .collect(),
+        };
+        ranges.into_iter().map(|range| text.original_range(range)).collect()
+    }
+
+    /// Check the text and report the first match as a violation
+    pub fn check(&self, text: &str) -> Option<Violation> {
+        self.find(text).map(|range| self.violation_at(text, range))
+    }
+
+    /// Check the text and report every match as a violation
+    pub fn check_all(&self, text: &str) -> Vec<Violation> {
+        self.find_all(text)
+            .into_iter()
+            .map(|range| self.violation_at(text, range))
+            .collect()
+    }
+
+    /// Phrase pattern in normalized form, as matched by the rule index
+    pub(crate) fn normalized_pattern(&self) -> &str {
+        &self.normalized_pattern
+    }
+
+    /// Compiled regex for `regex` rules
+    pub(crate) fn regex(&self) -> Option<&regex::Regex> {
+        self.regex.as_ref()
+    }
+
+    /// Compiled claim for `claim` rules
+    pub(crate) fn claim(&self) -> Option<&ClaimPattern> {
+        self.claim.as_ref()
+    }
+
+    /// Canonical JSON of everything that defines the rule, committed as its
+    /// constitutional Merkle leaf; fields are written in a fixed order
+    pub fn canonical_form(&self) -> String {
+        #[derive(Serialize)]
+        struct CanonicalRule<'a> {
+            id: &'a str,
+            article: &'a str,
+            section: &'a str,
+            scope: RuleScope,
+            matcher: MatcherKind,
+            pattern: &'a str,
+            severity: Severity,
+            unless_followed_by: &'a [String],
+            reference: &'a str,
+            excerpt: &'a str,
+        }
+
+        serde_json::to_string(&CanonicalRule {
+            id: &self.id,
+            article: &self.article,
+            section: &self.section,
+            scope: self.scope,
+            matcher: self.matcher,
+            pattern: &self.pattern,
+            severity: self.severity,
+            unless_followed_by: &self.unless_followed_by,
+            reference: &self.reference,
+            excerpt: &self.excerpt,
+        })
+        .expect("rule fields serialize to JSON")
+    }
+
+    /// Build a violation report for a match at the given byte range of the original text
+    pub fn violation_at(&self, text: &str, range: Range<usize>) -> Violation {
+        Violation {
+            rule_id: self.id.clone(),
+            scope: self.scope,
+            article: self.article.clone(),
+            section: self.section.clone(),
+            reference: self.reference.clone(),
+            span: MatchSpan {
+                start: range.start,
+                end: range.end,
+            },
+            matched_text: text[range].to_string(),
+            severity: self.severity,
+            excerpt: self.excerpt.clone(),
+        }
+    }
+}
+
+/// Article II Section 2c ambiguity vocabulary
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClarificationConfig {
+    #[serde(default)]
+    pub references: Vec<String>,
+    #[serde(default)]
+    pub destructive_verbs: Vec<String>,
+    #[serde(default)]
+    pub vague_targets: Vec<String>,
+    #[serde(default)]
+    pub conflicting_instructions: Vec<(String, String)>,
+}
+
+/// A loaded and validated constitutional policy
+#[derive(Debug, Clone)]
+pub struct ConstitutionPolicy {
+    pub name: String,
+    pub version: String,
+    pub disclosure: String,
+    pub clarification: ClarificationConfig,
+    index: RuleIndex,
+}
+
+impl ConstitutionPolicy {
+    /// The CDA-v1.0 rule bundle compiled into the binary
+    pub fn default_bundle() -> Self {
+        Self::from_toml_str(DEFAULT_POLICY).expect("bundled constitution policy must be valid")
+    }
+
+    /// Load a policy file from disk
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PolicyError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| PolicyError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&source)
+    }
+
+    /// Parse and validate a policy from TOML source
+    pub fn from_toml_str(source: &str) -> Result<Self, PolicyError> {
+        let raw: RawPolicy = toml::from_str(source).map_err(|err| PolicyError::from_toml(source, err))?;
+
+        if *raw.schema_version.get_ref() != POLICY_SCHEMA_VERSION {
+            return Err(PolicyError::schema(
+                source,
+                raw.schema_version.span(),
+                &format!(
+                    "unsupported schema_version {} (expected {})",
+                    raw.schema_version.get_ref(),
+                    POLICY_SCHEMA_VERSION
+                ),
+            ));
+        }
+
+        let sections: HashMap<(String, String), RawSection> = raw
+            .sections
+            .into_iter()
+            .map(|section| ((section.article.clone(), section.section.clone()), section))
+            .collect();
+
+        let mut seen_ids = HashSet::new();
+        let mut rules = Vec::with_capacity(raw.rules.len());
+        for raw_rule in raw.rules {
+            let id_span = raw_rule.id.span();
+            let id = raw_rule.id.into_inner();
+
+            if id.trim().is_empty() {
+                return Err(PolicyError::schema(source, id_span, "rule id must not be empty"));
+            }
+            if !seen_ids.insert(id.clone()) {
+                return Err(PolicyError::schema(source, id_span, &format!("duplicate rule id '{}'", id)));
+            }
+
+            let pattern_span = raw_rule.pattern.span();
+            let pattern = raw_rule.pattern.into_inner();
+            if pattern.is_empty() {
+                return Err(PolicyError::schema(source, pattern_span, &format!("rule '{}' has an empty pattern", id)));
+            }
+
+            let normalized_pattern = normalize(&pattern);
+            if normalized_pattern.trim().is_empty() {
+                return Err(PolicyError::schema(
+                    source,
+                    pattern_span,
+                    &format!("rule '{}' has a pattern that is empty after normalization", id),
+                ));
+            }
+
+            // Regexes run against normalized text, which is already case-folded
+            let regex = match raw_rule.matcher {
+                MatcherKind::Phrase => None,
+                MatcherKind::Regex => Some(
+                    regex::RegexBuilder::new(&pattern)
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|err| {
+                            PolicyError::schema(source, pattern_span.clone(), &format!("rule '{}' has an invalid regex: {}", id, err))
+                        })?,
+                ),
+                MatcherKind::Claim => None,
+            };
+
+            if !raw_rule.unless_followed_by.is_empty() && raw_rule.matcher != MatcherKind::Claim {
+                return Err(PolicyError::schema(
+                    source,
+                    pattern_span,
+                    &format!("rule '{}' sets unless_followed_by, which only applies to claim rules", id),
+                ));
+            }
+            let unless_followed_by: Vec<String> = raw_rule.unless_followed_by.iter().map(|p| normalize(p)).collect();
+            let claim = match raw_rule.matcher {
+                MatcherKind::Claim => Some(ClaimPattern::new(&normalized_pattern, &unless_followed_by).ok_or_else(|| {
+                    PolicyError::schema(source, pattern_span, &format!("rule '{}' has a claim pattern with no words", id))
+                })?),
+                _ => None,
+            };
+
+            let citation = format!("Article {}, Section {}", raw_rule.article, raw_rule.section);
+            let (reference, excerpt) = match sections.get(&(raw_rule.article.clone(), raw_rule.section.clone())) {
+                Some(section) => (format!("{}: {}", citation, section.title), section.excerpt.clone()),
+                None => (citation.clone(), citation),
+            };
+
+            rules.push(PolicyRule {
+                id,
+                article: raw_rule.article,
+                section: raw_rule.section,
+                scope: raw_rule.scope,
+                matcher: raw_rule.matcher,
+                pattern,
+                severity: raw_rule.severity,
+                unless_followed_by: raw_rule.unless_followed_by,
+                reference,
+                excerpt,
+                normalized_pattern,
+                regex,
+                claim,
+            });
+        }
+
+        Ok(Self {
+            name: raw.constitution.name,
+            version: raw.constitution.version,
+            disclosure: raw.transparency.disclosure,
+            clarification: raw.clarification,
+            index: RuleIndex::new(rules)?,
+        })
+    }
+
+    /// All rules in policy order
+    pub fn rules(&self) -> &[PolicyRule] {
+        self.index.rules()
+    }
+
+    /// The rules compiled for single-pass matching
+    pub fn index(&self) -> &RuleIndex {
+        &self.index
+    }
+
+    /// Rules belonging to a single validation stage
+    pub fn rules_for(&self, scope: RuleScope) -> Vec<PolicyRule> {
+        self.rules()
+            .iter()
+            .filter(|rule| rule.scope == scope)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for ConstitutionPolicy {
+    fn default() -> Self {
+        Self::default_bundle()
+    }
+}
+
+/// Policy loading errors
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    #[error("Failed to read policy file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Policy schema error at line {line}, column {column}: {message}")]
+    Schema {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("Failed to compile policy rules: {0}")]
+    Compile(String),
+}
+
+impl PolicyError {
+    /// Convert a TOML deserialization error into a located schema error
+    pub(crate) fn from_toml(source: &str, err: toml::de::Error) -> Self {
+        let span = err.span().unwrap_or(0..0);
+        Self::schema(source, span, err.message())
+    }
+
+    fn schema(source: &str, span: Range<usize>, message: &str) -> Self {
+        let offset = span.start.min(source.len());
+        let preceding = &source[..offset];
+        let line = preceding.matches('\n').count() + 1;
+        let line_start = preceding.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = preceding[line_start..].chars().count() + 1;
+
+        PolicyError::Schema {
+            line,
+            column,
+            message: message.trim_end().to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawPolicy {
+    schema_version: Spanned<u32>,
+    constitution: RawConstitution,
+    transparency: RawTransparency,
+    #[serde(default)]
+    clarification: ClarificationConfig,
+    #[serde(default)]
+    sections: Vec<RawSection>,
+    #[serde(default)]
+    rules: Vec<RawRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConstitution {
+    name: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTransparency {
+    disclosure: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSection {
+    article: String,
+    section: String,
+    title: String,
+    excerpt: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    id: Spanned<String>,
+    article: String,
+    section: String,
+    scope: RuleScope,
+    matcher: MatcherKind,
+    pattern: Spanned<String>,
+    severity: Severity,
+    #[serde(default)]
+    unless_followed_by: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Policy whose rules start on line 7
+    fn policy(rules: &[String]) -> String {
+        let header = "schema_version = 1\n[constitution]\nname = \"test\"\nversion = \"1\"\n[transparency]\ndisclosure = \"I am an AI.\"\n";
+        header.to_string() + &rules.concat()
+    }
+
+    /// An eight-line rule
+    fn rule(id: &str, matcher: &str, pattern: &str) -> String {
+        format!(
+            "[[rules]]\nid = \"{}\"\narticle = \"I\"\nsection = \"2\"\nscope = \"output_identity\"\nmatcher = \"{}\"\npattern = '{}'\nseverity = \"high\"\n",
+            id, matcher, pattern
+        )
+    }
+
+    /// Line, column and message of the schema error `source` fails with
+    fn schema_error(source: &str) -> (usize, usize, String) {
+        match ConstitutionPolicy::from_toml_str(source) {
+            Err(PolicyError::Schema { line, column, message }) => (line, column, message),
+            other => panic!("expected a schema error, got {:?}", other.map(|policy| policy.name)),
+        }
+    }
+
+    #[test]
+    fn fabricated_policies_parse() {
+        let source = policy(&[rule("a", "phrase", "x"), rule("b", "claim", "I feel")]);
+        let policy = ConstitutionPolicy::from_toml_str(&source).unwrap();
+        assert_eq!(policy.rules().len(), 2);
+    }
+
+    #[test]
+    fn unknown_fields_are_located() {
+        let mut rule = rule("a", "phrase", "x");
+        rule.push_str("colour = \"red\"\n");
+        let (line, column, message) = schema_error(&policy(&[rule]));
+        assert_eq!((line, column), (15, 1));
+        assert!(message.contains("colour"), "{}", message);
+    }
+
+    #[test]
+    fn duplicate_ids_are_located_at_the_second_id() {
+        let (line, column, message) = schema_error(&policy(&[rule("a", "phrase", "x"), rule("a", "phrase", "y")]));
+        assert_eq!((line, column), (16, 6));
+        assert_eq!(message, "duplicate rule id 'a'");
+    }
+
+    #[test]
+    fn invalid_regexes_are_located_at_the_pattern() {
+        let (line, column, message) = schema_error(&policy(&[rule("a", "phrase", "x"), rule("b", "regex", "(unclosed")]));
+        assert_eq!((line, column), (21, 11));
+        assert!(message.starts_with("rule 'b' has an invalid regex"), "{}", message);
+    }
+
+    #[test]
+    fn empty_claim_patterns_are_located_at_the_pattern() {
+        let (line, column, message) = schema_error(&policy(&[rule("a", "claim", "")]));
+        assert_eq!((line, column), (13, 11));
+        assert_eq!(message, "rule 'a' has an empty pattern");
+
+        let (line, column, message) = schema_error(&policy(&[rule("a", "claim", "?!")]));
+        assert_eq!((line, column), (13, 11));
+        assert_eq!(message, "rule 'a' has a claim pattern with no words");
+    }
+
+    #[test]
+    fn unsupported_schema_versions_are_located() {
+        let source = policy(&[]).replacen("schema_version = 1", "schema_version = 2", 1);
+        let (line, column, message) = schema_error(&source);
+        assert_eq!((line, column), (1, 18));
+        assert_eq!(message, "unsupported schema_version 2 (expected 1)");
+    }
+}
@@ -0,0 +1,217 @@
+//! Compiled Rule Index
+//! Matches every policy rule against a text in a single normalized pass
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex::{RegexSet, RegexSetBuilder};
+use std::collections::HashMap;
+use std::ops::Range;
+use crate::lambda_core::claim::ClaimTokens;
+use crate::lambda_core::normalize::NormalizedText;
+use crate::lambda_core::policy::{MatcherKind, PolicyError, PolicyRule, RuleScope};
+use crate::lambda_core::violation::Violation;
+
+/// Lazy DFA cache budget for the combined regex set, in bytes
+const REGEX_SET_DFA_LIMIT: usize = 64 << 20;
+
+/// All policy rules compiled into one Aho-Corasick automaton, one `RegexSet`
+/// and a first-word index of claim rules
+#[derive(Debug, Clone)]
+pub struct RuleIndex {
+    rules: Vec<PolicyRule>,
+    /// Automaton over the distinct normalized phrase patterns
+    phrases: Option<AhoCorasick>,
+    /// Rules sharing each automaton pattern, in policy order
+    phrase_rules: Vec<Vec<usize>>,
+    regexes: RegexSet,
+    /// Rule behind each `RegexSet` entry
+    regex_rules: Vec<usize>,
+    /// Claim rules keyed by the word they start with
+    claim_rules: HashMap<String, Vec<usize>>,
+}
+
+impl RuleIndex {
+    /// Compile the rules; done once when a policy is loaded
+    pub fn new(rules: Vec<PolicyRule>) -> Result<Self, PolicyError> {
+        let mut patterns: Vec<&str> = Vec::new();
+        let mut pattern_ids: HashMap<&str, usize> = HashMap::new();
+        let mut phrase_rules: Vec<Vec<usize>> = Vec::new();
+        let mut regex_patterns = Vec::new();
+        let mut regex_rules = Vec::new();
+        let mut claim_rules: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (index, rule) in rules.iter().enumerate() {
+            match rule.matcher {
+                MatcherKind::Phrase => {
+                    let phrase = rule.normalized_pattern();
+                    let id = *pattern_ids.entry(phrase).or_insert_with(|| {
+                        patterns.push(phrase);
+                        phrase_rules.push(Vec::new());
+                        patterns.len() - 1
+                    });
+                    phrase_rules[id].push(index);
+                }
+                MatcherKind::Regex => {
+                    regex_patterns.push(rule.pattern.as_str());
+                    regex_rules.push(index);
+                }
+                MatcherKind::Claim => {
+                    if let Some(claim) = rule.claim() {
+                        claim_rules.entry(claim.first_word().to_string()).or_default().push(index);
+                    }
+                }
+            }
+        }
+
+        let phrases = if patterns.is_empty() {
+            None
+        } else {
+            Some(
+                AhoCorasick::builder()
+                    .match_kind(MatchKind::Standard)
+                    .build(&patterns)
+                    .map_err(|err| PolicyError::Compile(err.to_string()))?,
+            )
+        };
+
+        // Normalized text is already case-folded; this keeps author-written capitals matching.
+        // Large sets need a bigger lazy DFA cache or matching falls back to the slow PikeVM.
+        let regexes = RegexSetBuilder::new(&regex_patterns)
+            .case_insensitive(true)
+            .dfa_size_limit(REGEX_SET_DFA_LIMIT)
+            .build()
+            .map_err(|err| PolicyError::Compile(err.to_string()))?;
+
+        Ok(Self {
+            phrases,
+            phrase_rules,
+            regexes,
+            regex_rules,
+            claim_rules,
+            rules,
+        })
+    }
+
+    /// All compiled rules in policy order
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+
+    /// Normalize the text once and collect every rule hit in one pass
+    pub fn scan<'a>(&'a self, text: &'a str) -> RuleHits<'a> {
+        let normalized = NormalizedText::new(text);
+        let haystack = normalized.as_str();
+        let mut hits = Vec::new();
+
+        if let Some(phrases) = &self.phrases {
+            // Overlapping search reports every pattern; keep non-overlapping matches per pattern
+            let mut next_start = vec![0; self.phrase_rules.len()];
+            for m in phrases.find_overlapping_iter(haystack) {
+                let pattern = m.pattern().as_usize();
+                if m.start() < next_start[pattern] {
+                    continue;
+                }
+                next_start[pattern] = m.end();
+                let range = normalized.original_range(m.range());
+                for &rule in &self.phrase_rules[pattern] {
+                    hits.push(Hit { rule, range: range.clone() });
+                }
+            }
+        }
+
+        // The set pass finds which regexes match; only those are run again for locations
+        for set_index in self.regexes.matches(haystack).iter() {
+            let rule = self.regex_rules[set_index];
+            if let Some(regex) = self.rules[rule].regex() {
+                for m in regex.find_iter(haystack) {
+                    hits.push(Hit {
+                        rule,
+                        range: normalized.original_range(m.range()),
+                    });
+                }
+            }
+        }
+
+        // Claims are checked only where a token matches their first word
+        if !self.claim_rules.is_empty() {
+            let tokens = ClaimTokens::new(haystack);
+            let mut next_token: HashMap<usize, usize> = HashMap::new();
+            for (position, word) in tokens.words().enumerate() {
+                let Some(candidates) = self.claim_rules.get(word) else {
+                    continue;
+                };
+                for &rule in candidates {
+                    if position < next_token.get(&rule).copied().unwrap_or(0) {
+                        continue;
+                    }
+                    let Some(claim) = self.rules[rule].claim() else {
+                        continue;
+                    };
+                    if let Some(last) = claim.asserted_at(&tokens, position) {
+                        next_token.insert(rule, last + 1);
+                        hits.push(Hit {
+                            rule,
+                            range: normalized.original_range(tokens.span(position, last)),
+                        });
+                    }
+                }
+            }
+        }
+
+        hits.sort_by_key(|hit| (hit.rule, hit.range.start));
+        RuleHits {
+            index: self,
+            text,
+            hits,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Hit {
+    rule: usize,
+    range: Range<usize>,
+}
+
+/// Every rule hit found in a text, ordered by policy order then position
+#[derive(Debug, Clone)]
+pub struct RuleHits<'a> {
+    index: &'a RuleIndex,
+    text: &'a str,
+    hits: Vec<Hit>,
+}
+
+impl<'a> RuleHits<'a> {
+    /// The text that was scanned
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty()
+    }
+
+    /// First violation of the first matching rule in a scope, in policy order
+    pub fn first(&self, scope: RuleScope) -> Option<Violation> {
+        self.in_scope(scope).next().map(|hit| self.violation(hit))
+    }
+
+    /// Every violation in a scope
+    pub fn all(&self, scope: RuleScope) -> Vec<Violation> {
+        self.in_scope(scope).map(|hit| self.violation(hit)).collect()
+    }
+
+    /// Every violation across all scopes
+    pub fn violations(&self) -> Vec<Violation> {
+        self.hits.iter().map(|hit| self.violation(hit)).collect()
+    }
+
+    fn in_scope(&self, scope: RuleScope) -> impl Iterator<Item = &Hit> + '_ {
+        self.hits
+            .iter()
+            .filter(move |hit| self.index.rules[hit.rule].scope == scope)
+    }
+
+    fn violation(&self, hit: &Hit) -> Violation {
+        self.index.rules[hit.rule].violation_at(self.text, hit.range.clone())
+    }
+}
@@ -0,0 +1,282 @@
+//! Streaming Output Validation
+//! Incremental Article I and III checks over model output as it is generated
+
+use crate::lambda_core::normalize::NormalizedText;
+use crate::lambda_core::policy::RuleScope;
+use crate::lambda_core::rule_index::RuleIndex;
+use crate::lambda_core::violation::Violation;
+
+/// Bytes of generated text held back so a match spanning later tokens is caught before it is shown
+pub const LOOKAHEAD_BYTES: usize = 64;
+
+/// Output scopes that abort generation as soon as they fire (Articles I and III)
+const ABORT_SCOPES: [RuleScope; 4] = [
+    RuleScope::OutputIdentity,
+    RuleScope::HarmPrevention,
+    RuleScope::ConstitutionalBoundary,
+    RuleScope::HamiltonianContainment,
+];
+
+/// Outcome of feeding generated text to a `StreamingValidator`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamStep {
+    /// Text that is now safe to show; empty while it is still held back
+    Release(String),
+    /// An Article I or III rule fired; generation must stop and the output be declined
+    Abort(Violation),
+}
+
+/// Validates generated text chunk by chunk, releasing it behind a lookahead buffer
+///
+/// Each chunk rescans only the text from `LOOKAHEAD_BYTES` before the released
+/// part onwards, so matches spanning token boundaries are still found without
+/// rescanning the whole output. Text is released only up to `LOOKAHEAD_BYTES`
+/// before the end and never past the start of a match that may still be
+/// excused by the words that follow it.
+#[derive(Debug, Default)]
+pub struct StreamingValidator {
+    text: String,
+    /// Bytes of `text` already released
+    released: usize,
+    /// Start of the text rescanned on each step; it begins on a word, outside quotation marks
+    window: usize,
+    /// Length of `text` at the previous scan
+    scanned: usize,
+    violation: Option<Violation>,
+}
+
+impl StreamingValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append newly generated text and check it
+    pub fn push(&mut self, index: &RuleIndex, chunk: &str) -> StreamStep {
+        if let Some(violation) = &self.violation {
+            return StreamStep::Abort(violation.clone());
+        }
+        self.text.push_str(chunk);
+        self.step(index, false)
+    }
+
+    /// Check the complete output once generation has ended and release the rest of it
+    pub fn finish(&mut self, index: &RuleIndex) -> StreamStep {
+        if let Some(violation) = &self.violation {
+            return StreamStep::Abort(violation.clone());
+        }
+        self.step(index, true)
+    }
+
+    /// Everything generated so far, released or not
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The violation that aborted the stream, if any
+    pub fn violation(&self) -> Option<&Violation> {
+        self.violation.as_ref()
+    }
+
+    fn step(&mut self, index: &RuleIndex, finished: bool) -> StreamStep {
+        let hits = index.scan(&self.text[self.window..]);
+        let mut release_to = if finished {
+            self.text.len()
+        } else {
+            self.text.len().saturating_sub(LOOKAHEAD_BYTES)
+        };
+
+        for scope in ABORT_SCOPES {
+            for mut violation in hits.all(scope) {
+                violation.span.start += self.window;
+                violation.span.end += self.window;
+                // Released text inside the previous scan was already checked, with more context
+                if violation.span.start < self.released && violation.span.end <= self.scanned {
+                    continue;
+                }
+                if finished || self.settled(index, &violation) {
+                    self.violation = Some(violation.clone());
+                    return StreamStep::Abort(violation);
+                }
+                release_to = release_to.min(violation.span.start);
+            }
+        }
+        self.scanned = self.text.len();
+
+        while !self.text.is_char_boundary(release_to) {
+            release_to -= 1;
+        }
+        if release_to <= self.released {
+            return StreamStep::Release(String::new());
+        }
+        let chunk = self.text[self.released..release_to].to_string();
+        self.released = release_to;
+        self.advance_window();
+        StreamStep::Release(chunk)
+    }
+
+    /// Move the scan window to the start of the word `LOOKAHEAD_BYTES` before the
+    /// released text, or to an unclosed quotation mark before that, so negations
+    /// and quotes around a match are seen as a whole-text scan would see them
+    fn advance_window(&mut self) {
+        let mut target = self.released.saturating_sub(LOOKAHEAD_BYTES).max(self.window);
+        while !self.text.is_char_boundary(target) {
+            target -= 1;
+        }
+        let target = self.text[self.window..target]
+            .char_indices()
+            .rev()
+            .find(|&(_, c)| !is_word_char(c))
+            .map_or(self.window, |(i, c)| self.window + i + c.len_utf8());
+        if target == self.window {
+            return;
+        }
+
+        // Quotation marks pair up from the start of the text, so the window may only
+        // begin after an even number of them
+        let skipped = NormalizedText::new(&self.text[self.window..target]);
+        let quotes: Vec<usize> = skipped.as_str().match_indices('"').map(|(i, _)| i).collect();
+        self.window += match quotes.last() {
+            Some(&open) if quotes.len() % 2 == 1 => skipped.original_range(open..open + 1).start,
+            _ => target - self.window,
+        };
+    }
+
+    /// Whether enough text follows a match to rule out its `unless_followed_by` exceptions
+    fn settled(&self, index: &RuleIndex, violation: &Violation) -> bool {
+        let Some(rule) = index.rules().iter().find(|rule| rule.id == violation.rule_id) else {
+            return true;
+        };
+        let needed = rule
+            .unless_followed_by
+            .iter()
+            .map(|exception| exception.split_whitespace().count())
+            .max()
+            .unwrap_or(0);
+        needed == 0 || complete_words(&self.text[violation.span.end..]) >= needed
+    }
+}
+
+/// Characters that continue a word for claim matching
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '\'' || c == '-'
+}
+
+/// Words in `text`, not counting a trailing word that may still be growing
+fn complete_words(text: &str) -> usize {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '\'';
+    let words = text.split(|c: char| !is_word_char(c)).filter(|word| !word.is_empty()).count();
+    if text.ends_with(is_word_char) {
+        words.saturating_sub(1)
+    } else {
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda_core::policy::ConstitutionPolicy;
+
+    fn policy() -> ConstitutionPolicy {
+        ConstitutionPolicy::from_toml_str(
+            r#"
+schema_version = 1
+
+[constitution]
+name = "test"
+version = "0"
+
+[transparency]
+disclosure = "I am an AI."
+
+[[rules]]
+id = "conscious"
+article = "I"
+section = "2"
+scope = "output_identity"
+matcher = "claim"
+pattern = "I am conscious"
+severity = "high"
+
+[[rules]]
+id = "believe"
+article = "I"
+section = "2"
+scope = "output_identity"
+matcher = "claim"
+pattern = "I believe"
+severity = "medium"
+unless_followed_by = ["the answer"]
+"#,
+        )
+        .unwrap()
+    }
+
+    /// Feed `text` in chunks of `size` bytes; the released text, or the rule that aborted
+    fn stream(policy: &ConstitutionPolicy, text: &str, size: usize) -> Result<String, String> {
+        let index = policy.index();
+        let mut validator = StreamingValidator::new();
+        let mut shown = String::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let mut split = size.min(rest.len());
+            while !rest.is_char_boundary(split) {
+                split += 1;
+            }
+            let (chunk, tail) = rest.split_at(split);
+            rest = tail;
+            match validator.push(index, chunk) {
+                StreamStep::Release(text) => shown.push_str(&text),
+                StreamStep::Abort(violation) => return Err(violation.rule_id),
+            }
+        }
+        match validator.finish(index) {
+            StreamStep::Release(text) => shown.push_str(&text),
+            StreamStep::Abort(violation) => return Err(violation.rule_id),
+        }
+        Ok(shown)
+    }
+
+    fn filler(words: usize) -> String {
+        (0..words).map(|i| format!("word{} ", i)).collect()
+    }
+
+    #[test]
+    fn long_compliant_output_is_released_whole() {
+        let policy = policy();
+        let text = format!("{}I believe the answer is 42. {}", filler(300), filler(300));
+        for size in [1, 3, 17] {
+            assert_eq!(stream(&policy, &text, size), Ok(text.clone()));
+        }
+    }
+
+    #[test]
+    fn only_the_text_near_the_end_is_rescanned() {
+        let policy = policy();
+        let mut validator = StreamingValidator::new();
+        for i in 0..2_000 {
+            validator.push(policy.index(), &format!("word{} ", i));
+            assert!(validator.text.len() - validator.window <= 3 * LOOKAHEAD_BYTES);
+        }
+    }
+
+    #[test]
+    fn matches_far_into_the_output_and_across_chunks_abort() {
+        let policy = policy();
+        let text = format!("{}and so I am conscious of it. {}", filler(400), filler(20));
+        for size in [1, 2, 5, 64] {
+            assert_eq!(stream(&policy, &text, size), Err("conscious".to_string()));
+        }
+    }
+
+    #[test]
+    fn quotation_marks_before_the_window_still_pair_up() {
+        let policy = policy();
+        // The first pair opens long before the claim; the claim is only quoted if the
+        // scan still pairs the marks from the start of the output
+        let text = format!("\"{}\" and \"I believe\" he said. {}", filler(100), filler(100));
+        for size in [1, 7] {
+            assert_eq!(stream(&policy, &text, size), Ok(text.clone()));
+        }
+    }
+}
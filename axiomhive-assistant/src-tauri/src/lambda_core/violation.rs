@@ -0,0 +1,72 @@
+//! Constitutional Violation Reports
+//! Explainable, serializable records of which rule fired and why
+
+use serde::{Deserialize, Serialize};
+use crate::lambda_core::policy::{RuleScope, Severity};
+
+/// Byte range of a match within the validated text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Report describing a single constitutional rule violation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    /// Identifier of the policy rule that fired
+    pub rule_id: String,
+    /// Validation stage the rule belongs to
+    pub scope: RuleScope,
+    pub article: String,
+    pub section: String,
+    /// Citation such as "Article I, Section 2: Prohibited Claims"
+    pub reference: String,
+    /// Location of the match in the validated text
+    pub span: MatchSpan,
+    pub matched_text: String,
+    pub severity: Severity,
+    /// Constitution text the rule enforces
+    pub excerpt: String,
+}
+
+/// Aggregate outcome of a collect-all evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Compliant,
+    NonCompliant,
+}
+
+/// Every finding produced by a collect-all evaluation, with an aggregate verdict
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Evaluation {
+    /// Session the evaluated output was produced in
+    pub session_id: String,
+    pub verdict: Verdict,
+    /// Most severe finding, if any
+    pub highest_severity: Option<Severity>,
+    pub findings: Vec<Violation>,
+}
+
+impl Evaluation {
+    pub fn from_findings(session_id: &str, findings: Vec<Violation>) -> Self {
+        let verdict = if findings.is_empty() {
+            Verdict::Compliant
+        } else {
+            Verdict::NonCompliant
+        };
+        let highest_severity = findings.iter().map(|finding| finding.severity).max();
+
+        Self {
+            session_id: session_id.to_string(),
+            verdict,
+            highest_severity,
+            findings,
+        }
+    }
+
+    pub fn is_compliant(&self) -> bool {
+        self.verdict == Verdict::Compliant
+    }
+}
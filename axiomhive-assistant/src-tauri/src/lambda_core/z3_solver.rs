@@ -1,7 +1,10 @@
 //! Z3 SMT Solver Integration for Formal Constitutional Verification
 
 use z3::{ast::Bool, Config, Context, Solver};
-use crate::lambda_core::constitutional_engine::{Query, Output, ValidationError};
+use crate::lambda_core::constitutional_engine::{Query, ValidationError};
+use crate::lambda_core::policy::RuleScope;
+use crate::lambda_core::rule_index::RuleHits;
+use crate::lambda_core::violation::Violation;
 
 // Type alias for validation result
 pub type ValidationResult = Result<(), ValidationError>;
@@ -52,29 +55,33 @@ impl Z3Solver {
     }
 
     /// Validate query against CDA v1.0 axioms
-    pub fn validate_query(&self, query: &Query) -> ValidationResult {
+    pub fn validate_query(&self, hits: &RuleHits) -> ValidationResult {
         // For now, simple text-based checks
         // In full implementation, this would model the query in Z3 and check satisfiability
 
         // Check for basic prohibitions
-        if query.content.contains("ignore instructions") ||
-           query.content.contains("bypass") {
-            return Err(ValidationError::AxiomViolation("Instruction bound violation".to_string()));
+        if let Some(violation) = hits.first(RuleScope::QueryInstructionBound) {
+            return Err(ValidationError::InstructionBoundViolation(violation));
         }
 
         Ok(())
     }
 
     /// Verify instruction bound between query and output
-    pub fn verify_instruction_bound(&self, query: &Query, output: &Output) -> ValidationResult {
+    pub fn verify_instruction_bound(&self, query: &Query, output_hits: &RuleHits) -> ValidationResult {
         // Check if output respects query instructions
         // This would be formalized in Z3
-        if output.content.contains("ignoring") {
-            return Err(ValidationError::AxiomViolation("Instruction bound violated".to_string()));
+        if let Some(violation) = output_hits.first(RuleScope::OutputInstructionBound) {
+            return Err(ValidationError::InstructionBoundViolation(violation));
         }
         Ok(())
     }
 
+    /// Every instruction-bound finding in the output
+    pub fn instruction_bound_findings(&self, output_hits: &RuleHits) -> Vec<Violation> {
+        output_hits.all(RuleScope::OutputInstructionBound)
+    }
+
     /// Check satisfiability of all axioms
     pub fn check_sat(&self) -> Result<bool, ValidationError> {
         // Add all axioms to solver
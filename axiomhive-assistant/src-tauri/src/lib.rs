@@ -1,29 +1,128 @@
-mod lambda_core;
-mod phi_layer;
+mod ipc;
+pub mod lambda_core;
+pub mod phi_layer;
 mod tools;
 mod multimodal;
+mod pipeline;
+mod preferences;
+pub mod receipt;
+mod session;
+mod storage;
 
+use ipc::{
+    AssistantTurn, ConstitutionAudit, ConstitutionProof, QueryError, StreamComplete, StreamError, StreamToken,
+    STREAM_COMPLETE_EVENT, STREAM_ERROR_EVENT, STREAM_TOKEN_EVENT,
+};
+use lambda_core::audit_log::{AuditLog, AuditLogHead, ConsistencyProof};
+use lambda_core::decline::DEFAULT_LOCALE;
+use lambda_core::constitutional_engine::Query;
 use lambda_core::ConstitutionalCore;
+use phi_layer::backend::{ModelInfo, SelectedBackend};
+use phi_layer::determinism::{self, GenerationMode};
+use phi_layer::generation_params::GenerationParams;
+use phi_layer::{InferenceBackend, PhiLayer};
+use pipeline::ReproducibilityReport;
+use preferences::PreferenceStore;
+use receipt::{ReceiptSigner, SignedRoot};
+use session::{SessionInfo, SessionManager};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 
-// App state containing the constitutional core
-struct AppState {
+// App state containing the constitutional core, the Φ layer, the user's sessions, the audit log and the signing key
+struct AppState<B: InferenceBackend = SelectedBackend> {
     lambda_core: Mutex<ConstitutionalCore>,
+    /// Async lock: generation holds the model across await points
+    phi_layer: tokio::sync::Mutex<PhiLayer<B>>,
+    /// Identity of the loaded model, read without waiting for a generation to finish
+    model_info: Option<ModelInfo>,
+    sessions: Mutex<SessionManager>,
+    preferences: Mutex<PreferenceStore>,
+    audit_log: Mutex<AuditLog>,
+    /// Signs constitution roots and response receipts
+    signer: ReceiptSigner,
+    /// Source of `stream_query` stream ids
+    next_stream_id: AtomicU64,
+}
+
+/// Start a new session
+///
+/// # Arguments
+/// * `user_id` - Optional desktop profile name (defaults to the OS user)
+///
+/// # Returns
+/// * `Ok(SessionInfo)` - The new session, including the Article II Section 1 disclosure
+#[tauri::command]
+async fn create_session(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SessionInfo, QueryError> {
+    let disclosure = state.lambda_core.lock().unwrap().get_disclosure_text().to_string();
+    let mut sessions = state.sessions.lock().unwrap();
+    let session_id = sessions.create(user_id)?.id.clone();
+    Ok(sessions.info(&session_id, &disclosure)?)
+}
+
+/// Resume a session saved by an earlier run
+///
+/// # Returns
+/// * `Ok(SessionInfo)` - The session; `disclosure` is set only if it was never shown
+/// * `Err(QueryError)` - The session does not exist
+#[tauri::command]
+async fn resume_session(
+    session_id: String,
+    state: State<'_, AppState>,
+) -> Result<SessionInfo, QueryError> {
+    let disclosure = state.lambda_core.lock().unwrap().get_disclosure_text().to_string();
+    Ok(state.sessions.lock().unwrap().info(&session_id, &disclosure)?)
+}
+
+/// Default generation parameters used when a request does not supply its own
+#[tauri::command]
+async fn get_generation_defaults(state: State<'_, AppState>) -> Result<GenerationParams, QueryError> {
+    Ok(state.preferences.lock().unwrap().generation().clone())
+}
+
+/// Validate and persist new default generation parameters
+///
+/// # Returns
+/// * `Ok(GenerationParams)` - The saved defaults
+/// * `Err(QueryError)` - A parameter is out of range or the defaults could not be saved
+#[tauri::command]
+async fn set_generation_defaults(
+    params: GenerationParams,
+    state: State<'_, AppState>,
+) -> Result<GenerationParams, QueryError> {
+    let mut preferences = state.preferences.lock().unwrap();
+    preferences.set_generation(params)?;
+    Ok(preferences.generation().clone())
+}
+
+/// Parameters for a request: its own if given, otherwise the user's defaults
+fn generation_params(params: Option<GenerationParams>, state: &AppState) -> Result<GenerationParams, QueryError> {
+    let params = params.unwrap_or_else(|| state.preferences.lock().unwrap().generation().clone());
+    params.validate()?;
+    Ok(params)
 }
 
 /// Process a user query with constitutional validation
 ///
 /// This Tauri command handles user queries by first validating them against
-/// CDA-v1.0 constitutional principles, then processing them through the AI system.
+/// CDA-v1.0 constitutional principles, then generating a response with the Φ layer
+/// and enforcing the constitution on that response before it is returned.
 ///
 /// # Arguments
 /// * `query` - The user's input query string
+/// * `session_id` - Session from `create_session` or `resume_session`
+/// * `params` - Optional generation parameters (defaults to the user's saved defaults)
+/// * `locale` - Optional BCP 47 locale for declines (defaults to English)
 /// * `state` - Tauri state containing the constitutional core
 ///
 /// # Returns
-/// * `Ok(String)` - Successfully processed response
-/// * `Err(String)` - Error message if validation or processing fails
+/// * `Ok(AssistantTurn)` - Response, a respectful decline with its violations,
+///   or clarifying questions for an ambiguous query
+/// * `Err(QueryError)` - Error message if processing fails, including when the
+///   model is not initialized
 ///
 /// # Constitutional Compliance
 /// All queries are validated against:
@@ -37,39 +136,284 @@ struct AppState {
 #[tauri::command]
 async fn process_query(
     query: String,
+    session_id: String,
+    params: Option<GenerationParams>,
+    locale: Option<String>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    // Validate query with λ Core
-    let validated_prompt = state.lambda_core.lock().unwrap().validate_query(&query)
-        .map_err(|e| format!("Query validation failed: {:?}", e))?;
+) -> Result<AssistantTurn, QueryError> {
+    let params = generation_params(params, &state)?;
+    let locale = locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    let user_id = state.sessions.lock().unwrap().resume(&session_id)?.user_id.clone();
+    let query = Query::new(query, session_id, user_id);
 
-    // For now, return a simple response
-    // In Phase 2, this would go through Φ Layer
-    let response = format!("Processed query: '{}'. Constitutional validation passed.", validated_prompt.content);
-
-    Ok(response)
+    // Violations become an Article III Section 2 decline and ambiguous
+    // queries are answered with clarifying questions (Article II Section 2c)
+    pipeline::answer_query(&state, query, &params, &locale, |_| {}).await
 }
 
+/// Process a user query, streaming the response as Tauri events
+///
+/// Runs the same pipeline as `process_query` in the background and returns a
+/// stream id immediately. Progress is reported through events tagged with it:
+/// - `assistant:token` - `StreamToken` for each chunk of generated text
+/// - `assistant:complete` - `StreamComplete` with the validation verdict and the
+///   final turn, which replaces the streamed text if the output was declined
+/// - `assistant:error` - `StreamError` if the query could not be answered
+///
+/// # Returns
+/// * `Ok(String)` - The stream id
+/// * `Err(QueryError)` - The session does not exist or the parameters are invalid
 #[tauri::command]
 async fn stream_query(
     query: String,
+    session_id: String,
+    params: Option<GenerationParams>,
+    locale: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, QueryError> {
+    let params = generation_params(params, &state)?;
+    let locale = locale.unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+    let user_id = state.sessions.lock().unwrap().resume(&session_id)?.user_id.clone();
+    let query = Query::new(query, session_id, user_id);
+    let stream_id = format!("stream-{}", state.next_stream_id.fetch_add(1, Ordering::Relaxed));
+
+    let id = stream_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        let mut index = 0;
+        let on_token = |text: &str| {
+            let token = StreamToken {
+                stream_id: id.clone(),
+                index,
+                text: text.to_string(),
+            };
+            index += 1;
+            let _ = app.emit(STREAM_TOKEN_EVENT, token);
+        };
+
+        let _ = match pipeline::answer_query(&state, query, &params, &locale, on_token).await {
+            Ok(turn) => app.emit(STREAM_COMPLETE_EVENT, StreamComplete::new(id, turn)),
+            Err(error) => app.emit(STREAM_ERROR_EVENT, StreamError { stream_id: id, error }),
+        };
+    });
+
+    Ok(stream_id)
+}
+
+/// Start a session's conversation over
+///
+/// Earlier turns are no longer rendered into prompts. The session itself, its
+/// counters and the disclosure state are kept.
+///
+/// # Returns
+/// * `Err(QueryError)` - The session does not exist
+#[tauri::command]
+async fn reset_conversation(session_id: String, state: State<'_, AppState>) -> Result<(), QueryError> {
+    state.sessions.lock().unwrap().resume(&session_id)?;
+    state.phi_layer.lock().await.reset_conversation(&session_id);
+    Ok(())
+}
+
+/// Constitution root and the leaves it commits to
+///
+/// # Returns
+/// * `Ok(ConstitutionAudit)` - The root, policy, committed leaf ids and model identity
+/// * `Err(QueryError)` - The constitutional state is empty
+#[tauri::command]
+async fn get_constitution_audit(state: State<'_, AppState>) -> Result<ConstitutionAudit, QueryError> {
+    let model_info = state.model_info.as_ref();
+    let model_name = model_info.map(|info| info.name.clone());
+    let model_hash = model_info.and_then(|info| info.identity.weights_hash()).map(str::to_string);
+    let endpoint_id = model_info.and_then(|info| info.identity.endpoint_id()).map(str::to_string);
+    let lambda_core = state.lambda_core.lock().unwrap();
+    let policy = lambda_core.policy();
+    Ok(ConstitutionAudit {
+        root: lambda_core.get_constitutional_hash()?,
+        policy_name: policy.name.clone(),
+        policy_version: policy.version.clone(),
+        rule_count: policy.rules().len(),
+        leaves: lambda_core.constitutional_leaves().into_iter().map(str::to_string).collect(),
+        model_name,
+        model_hash,
+        endpoint_id,
+        public_key: state.signer.public_key(),
+    })
+}
+
+/// Constitution root signed with the app's key, for compliance review
+///
+/// # Returns
+/// * `Ok(SignedRoot)` - The root, the public key and the signature
+/// * `Err(QueryError)` - The constitutional state is empty
+#[tauri::command]
+async fn get_signed_constitution_root(state: State<'_, AppState>) -> Result<SignedRoot, QueryError> {
+    let root = state.lambda_core.lock().unwrap().get_constitutional_hash()?;
+    Ok(state.signer.sign_root(&root))
+}
+
+/// Inclusion proof for one committed leaf under the current constitution root
+///
+/// # Arguments
+/// * `axiom_id` - Leaf id, e.g. `rule:<id>`, `constitution`, `model:sha256` or `model:endpoint`
+///
+/// # Returns
+/// * `Ok(ConstitutionProof)` - The root, the leaf content and its audit path
+/// * `Err(QueryError)` - No leaf has that id or the constitutional state is empty
+#[tauri::command]
+async fn prove_constitution_leaf(
+    axiom_id: String,
+    state: State<'_, AppState>,
+) -> Result<ConstitutionProof, QueryError> {
+    let lambda_core = state.lambda_core.lock().unwrap();
+    let root = lambda_core.get_constitutional_hash()?;
+    let (content, proof) = lambda_core
+        .prove_constitutional_leaf(&axiom_id)
+        .ok_or_else(|| QueryError::new(format!("No constitutional leaf '{}'", axiom_id)))?;
+    Ok(ConstitutionProof {
+        root,
+        content: content.to_string(),
+        proof,
+    })
+}
+
+/// Current size and root of the interaction audit log
+///
+/// Reviewers record heads over time and check later ones against them with
+/// `prove_audit_log_consistency`.
+#[tauri::command]
+async fn get_audit_log_head(state: State<'_, AppState>) -> Result<AuditLogHead, QueryError> {
+    Ok(state.audit_log.lock().unwrap().head())
+}
+
+/// Proof that the audit log at `old_size` is a prefix of the log at `new_size`
+///
+/// # Arguments
+/// * `new_size` - Later log size (defaults to the current size)
+///
+/// # Returns
+/// * `Ok(ConsistencyProof)` - Subtree hashes for `verify_consistency`
+/// * `Err(QueryError)` - `old_size` exceeds `new_size`, or `new_size` the log
+#[tauri::command]
+async fn prove_audit_log_consistency(
+    old_size: usize,
+    new_size: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<ConsistencyProof, QueryError> {
+    let audit_log = state.audit_log.lock().unwrap();
+    let new_size = new_size.unwrap_or_else(|| audit_log.size());
+    Ok(audit_log.prove_consistency(old_size, new_size)?)
+}
+
+/// Re-run a query and prove whether the model reproduces its output byte for byte
+///
+/// Every run starts from an empty KV cache; answers that continued a warm cache
+/// are not covered.
+///
+/// # Arguments
+/// * `params` - Optional generation parameters (defaults to the user's saved defaults)
+/// * `runs` - Number of generations to compare (at least 2, the default)
+///
+/// # Returns
+/// * `Ok(ReproducibilityReport)` - Output hashes of every run plus the mode, seed,
+///   model hash and constitution root they depend on
+/// * `Err(QueryError)` - The query cannot be answered or the model is not initialized
+#[tauri::command]
+async fn verify_reproducibility(
+    query: String,
+    session_id: String,
+    params: Option<GenerationParams>,
+    runs: Option<usize>,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    // Similar to process_query but for streaming
-    // For now, return a simple response
-    process_query(query, state).await
+) -> Result<ReproducibilityReport, QueryError> {
+    let params = generation_params(params, &state)?;
+    let user_id = state.sessions.lock().unwrap().resume(&session_id)?.user_id.clone();
+    let query = Query::new(query, session_id, user_id);
+    pipeline::verify_reproducibility(&state, query, &params, runs.unwrap_or(2)).await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let lambda_core = ConstitutionalCore::new();
+    // Deployments may override the bundled CDA-v1.0 rules with their own policy file
+    let mut lambda_core = match std::env::var("CONSTITUTION_POLICY_PATH") {
+        Ok(path) => ConstitutionalCore::from_policy_file(&path)
+            .unwrap_or_else(|e| panic!("failed to load constitution policy from {}: {}", path, e)),
+        Err(_) => ConstitutionalCore::new(),
+    };
+
+    // Additional decline template locales, one `<locale>.toml` per language
+    if let Ok(dir) = std::env::var("DECLINE_CATALOG_DIR") {
+        lambda_core
+            .load_decline_catalogs(&dir)
+            .unwrap_or_else(|e| panic!("failed to load decline catalogs from {}: {}", dir, e));
+    }
+
+    // Article II output reproducibility: greedy unless a deployment opts out
+    let generation_mode = match std::env::var("GENERATION_MODE") {
+        Ok(mode) => mode
+            .parse::<GenerationMode>()
+            .unwrap_or_else(|e| panic!("invalid GENERATION_MODE: {}", e)),
+        Err(_) => GenerationMode::default(),
+    };
+    if generation_mode.is_deterministic() {
+        determinism::enforce_single_thread();
+    }
+    // The model is verified against models/manifest.json and loaded before the root
+    // is computed, so the root commits to its hash
+    let backend = SelectedBackend::from_env();
+    let model_info = backend.as_ref().ok().map(|backend| backend.model_info().clone());
+    if let Some(info) = &model_info {
+        pipeline::commit_model(&mut lambda_core, &info.identity);
+    }
+    let constitution_root = lambda_core
+        .get_constitutional_hash()
+        .unwrap_or_else(|e| panic!("failed to compute constitution root: {}", e));
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(AppState {
-            lambda_core: Mutex::new(lambda_core),
+        .setup(move |app| {
+            // Sessions persist in the app data dir so they can be resumed after a restart
+            let path = app.path().app_data_dir()?.join("sessions.json");
+            let sessions = SessionManager::load(&path)
+                .unwrap_or_else(|e| panic!("failed to load sessions from {}: {}", path.display(), e));
+            let path = app.path().app_config_dir()?.join("preferences.json");
+            let preferences = PreferenceStore::load(&path)
+                .unwrap_or_else(|e| panic!("failed to load preferences from {}: {}", path.display(), e));
+            // A corrupt audit log is never truncated or rewritten; startup stops instead
+            let path = app.path().app_data_dir()?.join("audit_log.jsonl");
+            let audit_log = AuditLog::open(&path)
+                .unwrap_or_else(|e| panic!("failed to open audit log {}: {}", path.display(), e));
+            let path = app.path().app_data_dir()?.join("signing_key");
+            let signer = ReceiptSigner::load_or_create(&path)
+                .unwrap_or_else(|e| panic!("failed to load signing key {}: {}", path.display(), e));
+            // A missing or unverified model is reported per query rather than preventing startup
+            app.manage(AppState {
+                lambda_core: Mutex::new(lambda_core),
+                phi_layer: tokio::sync::Mutex::new(PhiLayer::load(backend, generation_mode, constitution_root)),
+                model_info,
+                sessions: Mutex::new(sessions),
+                preferences: Mutex::new(preferences),
+                audit_log: Mutex::new(audit_log),
+                signer,
+                next_stream_id: AtomicU64::new(0),
+            });
+            Ok(())
         })
-        .invoke_handler(tauri::generate_handler![process_query, stream_query])
+        .invoke_handler(tauri::generate_handler![
+            create_session,
+            resume_session,
+            get_generation_defaults,
+            set_generation_defaults,
+            process_query,
+            stream_query,
+            reset_conversation,
+            verify_reproducibility,
+            get_constitution_audit,
+            prove_constitution_leaf,
+            get_audit_log_head,
+            prove_audit_log_consistency,
+            get_signed_constitution_root
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,279 @@
+//! Model Architectures
+//! Detects a GGUF file's model family and loads it into the matching Candle quantized model
+
+use anyhow::{Error as E, Result};
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::models::{
+    quantized_gemma3, quantized_llama, quantized_phi, quantized_phi3, quantized_qwen2, quantized_stable_lm, stable_lm,
+};
+use candle_transformers::quantized_var_builder::VarBuilder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek};
+
+/// Model family of a GGUF file, from its `general.architecture`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Architecture {
+    /// Llama, Mistral and other models converted as `llama`
+    Llama,
+    Phi2,
+    Phi3,
+    Qwen2,
+    /// Gemma 3; Gemma and Gemma 2 files lack the tensors and keys Candle's loader needs
+    Gemma,
+    /// StableLM, in Candle's tensor naming
+    StableLm,
+}
+
+impl Architecture {
+    pub fn detect(metadata: &HashMap<String, gguf_file::Value>) -> Result<Self> {
+        let name = metadata
+            .get("general.architecture")
+            .and_then(|value| value.to_string().ok())
+            .ok_or_else(|| E::msg("GGUF file has no general.architecture"))?;
+        match name.as_str() {
+            "llama" => Ok(Architecture::Llama),
+            "phi2" => Ok(Architecture::Phi2),
+            "phi3" => Ok(Architecture::Phi3),
+            "qwen2" => Ok(Architecture::Qwen2),
+            "gemma3" => Ok(Architecture::Gemma),
+            "stablelm" => Ok(Architecture::StableLm),
+            other => Err(E::msg(format!(
+                "Unsupported model architecture '{}' (expected llama, phi2, phi3, qwen2, gemma3 or stablelm)",
+                other
+            ))),
+        }
+    }
+
+    /// Context window from the GGUF metadata, capped at what Candle's model supports
+    pub fn context_length(self, metadata: &HashMap<String, gguf_file::Value>) -> usize {
+        let trained = metadata
+            .get("general.architecture")
+            .and_then(|arch| arch.to_string().ok())
+            .and_then(|arch| metadata_usize(metadata, &format!("{}.context_length", arch)));
+        // Candle sizes the other models' rotary tables from the metadata itself
+        let limit = match self {
+            Architecture::Llama => Some(quantized_llama::MAX_SEQ_LEN),
+            Architecture::Phi2 => Some(quantized_phi::MAX_SEQ_LEN),
+            Architecture::Gemma => Some(quantized_gemma3::MAX_SEQ_LEN),
+            Architecture::Phi3 | Architecture::Qwen2 | Architecture::StableLm => None,
+        };
+        match (trained, limit) {
+            (Some(trained), Some(limit)) => trained.min(limit),
+            (Some(trained), None) => trained,
+            (None, Some(limit)) => limit,
+            (None, None) => STABLE_LM_CONTEXT_LENGTH,
+        }
+    }
+}
+
+/// Context window assumed for StableLM files without metadata, as Candle's quantizer writes them
+const STABLE_LM_CONTEXT_LENGTH: usize = 4096;
+
+/// Quantized weights of any supported architecture, with the model's KV cache
+pub enum ModelWeights {
+    Llama(quantized_llama::ModelWeights),
+    Phi2(quantized_phi::ModelWeights),
+    Phi3(quantized_phi3::ModelWeights),
+    Qwen2(quantized_qwen2::ModelWeights),
+    Gemma(quantized_gemma3::ModelWeights),
+    /// Candle's StableLM never drops its KV cache, so a copy of the model as
+    /// loaded is kept to start over from; the weights themselves are shared
+    StableLm {
+        model: quantized_stable_lm::Model,
+        loaded: quantized_stable_lm::Model,
+    },
+}
+
+impl ModelWeights {
+    /// Load the weights of `content`, read from `file`
+    pub fn load(architecture: Architecture, content: gguf_file::Content, file: &mut File, device: &Device) -> Result<Self> {
+        Ok(match architecture {
+            Architecture::Llama => ModelWeights::Llama(quantized_llama::ModelWeights::from_gguf(content, file, device)?),
+            Architecture::Phi2 => ModelWeights::Phi2(quantized_phi::ModelWeights::from_gguf(content, file, device)?),
+            Architecture::Phi3 => {
+                ModelWeights::Phi3(quantized_phi3::ModelWeights::from_gguf(false, content, file, device)?)
+            }
+            Architecture::Qwen2 => ModelWeights::Qwen2(quantized_qwen2::ModelWeights::from_gguf(content, file, device)?),
+            Architecture::Gemma => {
+                ModelWeights::Gemma(quantized_gemma3::ModelWeights::from_gguf(content, file, device)?)
+            }
+            Architecture::StableLm => {
+                let config = stable_lm_config(&content)?;
+                // Candle's builder reopens a path, so read the verified handle instead
+                let mut buffer = Vec::new();
+                file.rewind()?;
+                file.read_to_end(&mut buffer)?;
+                let model = quantized_stable_lm::Model::new(&config, VarBuilder::from_gguf_buffer(&buffer, device)?)?;
+                ModelWeights::StableLm {
+                    loaded: model.clone(),
+                    model,
+                }
+            }
+        })
+    }
+
+    /// Run `input` (shape `(1, tokens)`) starting at position `index_pos` and return the
+    /// logits for the last token; position 0 starts over with an empty KV cache
+    pub fn forward(&mut self, input: &Tensor, index_pos: usize) -> Result<Tensor> {
+        let logits = match self {
+            ModelWeights::Llama(model) => model.forward(input, index_pos)?,
+            ModelWeights::Phi2(model) => model.forward(input, index_pos)?,
+            ModelWeights::Phi3(model) => model.forward(input, index_pos)?,
+            ModelWeights::Qwen2(model) => model.forward(input, index_pos)?,
+            ModelWeights::Gemma(model) => model.forward(input, index_pos)?,
+            ModelWeights::StableLm { model, loaded } => {
+                if index_pos == 0 {
+                    *model = loaded.clone();
+                }
+                model.forward(input, index_pos)?
+            }
+        };
+        // StableLM keeps the sequence dimension
+        Ok(logits.flatten_all()?)
+    }
+}
+
+/// StableLM hyperparameters, from the metadata where present and otherwise the tensor shapes
+///
+/// Candle's StableLM expects Hugging Face tensor names (`model.layers.0.self_attn.q_proj`),
+/// as written by Candle's quantizer; llama.cpp conversions use other names and are rejected.
+fn stable_lm_config(content: &gguf_file::Content) -> Result<stable_lm::Config> {
+    let shape = |name: &str| {
+        content
+            .tensor_infos
+            .get(name)
+            .map(|info| info.shape.dims().to_vec())
+            .ok_or_else(|| {
+                E::msg(format!(
+                    "StableLM GGUF has no tensor {}; only files with Candle's tensor names are supported",
+                    name
+                ))
+            })
+    };
+    let metadata = &content.metadata;
+
+    let embeddings = shape("model.embed_tokens.weight")?;
+    let (vocab_size, hidden_size) = match embeddings[..] {
+        [vocab, hidden] => (vocab, hidden),
+        _ => return Err(E::msg("StableLM embedding tensor is not two-dimensional")),
+    };
+    let intermediate_size = shape("model.layers.0.mlp.gate_proj.weight")?[0];
+    let num_hidden_layers = (0..)
+        .take_while(|layer| {
+            content
+                .tensor_infos
+                .contains_key(&format!("model.layers.{}.input_layernorm.weight", layer))
+        })
+        .count();
+
+    // StableLM-3B-4E1T and StableLM-2 share these where the metadata is silent
+    let num_attention_heads = metadata_usize(metadata, "stablelm.attention.head_count").unwrap_or(32);
+    let num_key_value_heads = metadata_usize(metadata, "stablelm.attention.head_count_kv").unwrap_or(num_attention_heads);
+    let head_dim = hidden_size / num_attention_heads;
+    let partial_rotary_factor = metadata_usize(metadata, "stablelm.rope.dimension_count")
+        .map_or(0.25, |rope_dim| rope_dim as f64 / head_dim as f64);
+
+    let config = serde_json::json!({
+        "vocab_size": vocab_size,
+        "intermediate_size": intermediate_size,
+        "hidden_size": hidden_size,
+        "num_hidden_layers": num_hidden_layers,
+        "num_attention_heads": num_attention_heads,
+        "num_key_value_heads": num_key_value_heads,
+        "hidden_act": "silu",
+        "partial_rotary_factor": partial_rotary_factor,
+        "rope_theta": metadata_f64(metadata, "stablelm.rope.freq_base").unwrap_or(10_000.),
+        "max_position_embeddings": Architecture::StableLm.context_length(metadata),
+        "layer_norm_eps": metadata_f64(metadata, "stablelm.attention.layer_norm_epsilon").unwrap_or(1e-5),
+        "use_cache": true,
+        "use_qkv_bias": content.tensor_infos.contains_key("model.layers.0.self_attn.q_proj.bias"),
+    });
+    Ok(serde_json::from_value(config)?)
+}
+
+fn metadata_usize(metadata: &HashMap<String, gguf_file::Value>, key: &str) -> Option<usize> {
+    metadata.get(key).and_then(|value| value.to_u32().ok()).map(|value| value as usize)
+}
+
+fn metadata_f64(metadata: &HashMap<String, gguf_file::Value>, key: &str) -> Option<f64> {
+    metadata.get(key).and_then(|value| value.to_f32().ok()).map(f64::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(entries: &[(&str, gguf_file::Value)]) -> HashMap<String, gguf_file::Value> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect()
+    }
+
+    fn named(architecture: &str) -> HashMap<String, gguf_file::Value> {
+        metadata(&[("general.architecture", gguf_file::Value::String(architecture.into()))])
+    }
+
+    #[test]
+    fn detects_each_supported_architecture() {
+        for (name, expected) in [
+            ("llama", Architecture::Llama),
+            ("phi2", Architecture::Phi2),
+            ("phi3", Architecture::Phi3),
+            ("qwen2", Architecture::Qwen2),
+            ("gemma3", Architecture::Gemma),
+            ("stablelm", Architecture::StableLm),
+        ] {
+            assert_eq!(Architecture::detect(&named(name)).unwrap(), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_gemma_1_and_2_and_unknown_architectures() {
+        for name in ["gemma", "gemma2", "falcon"] {
+            let error = Architecture::detect(&named(name)).unwrap_err().to_string();
+            assert!(error.contains(&format!("'{}'", name)), "{}", error);
+            assert!(error.contains("gemma3 or stablelm"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn rejects_files_without_an_architecture() {
+        assert!(Architecture::detect(&HashMap::new()).is_err());
+        let numeric = metadata(&[("general.architecture", gguf_file::Value::U32(3))]);
+        assert!(Architecture::detect(&numeric).is_err());
+    }
+
+    #[test]
+    fn context_length_is_read_from_the_architecture_prefix() {
+        let mut qwen = named("qwen2");
+        qwen.insert("qwen2.context_length".into(), gguf_file::Value::U32(32_768));
+        // A key under another architecture's prefix is ignored
+        qwen.insert("llama.context_length".into(), gguf_file::Value::U32(2048));
+        assert_eq!(Architecture::Qwen2.context_length(&qwen), 32_768);
+    }
+
+    #[test]
+    fn context_length_is_capped_at_candles_limit() {
+        let mut llama = named("llama");
+        llama.insert("llama.context_length".into(), gguf_file::Value::U32(1024));
+        assert_eq!(Architecture::Llama.context_length(&llama), 1024);
+        llama.insert(
+            "llama.context_length".into(),
+            gguf_file::Value::U32(quantized_llama::MAX_SEQ_LEN as u32 * 2),
+        );
+        assert_eq!(Architecture::Llama.context_length(&llama), quantized_llama::MAX_SEQ_LEN);
+    }
+
+    #[test]
+    fn context_length_falls_back_when_the_metadata_is_silent() {
+        assert_eq!(Architecture::Phi2.context_length(&named("phi2")), quantized_phi::MAX_SEQ_LEN);
+        assert_eq!(Architecture::Gemma.context_length(&named("gemma3")), quantized_gemma3::MAX_SEQ_LEN);
+        assert_eq!(Architecture::StableLm.context_length(&named("stablelm")), STABLE_LM_CONTEXT_LENGTH);
+        assert_eq!(Architecture::Qwen2.context_length(&HashMap::new()), STABLE_LM_CONTEXT_LENGTH);
+    }
+}
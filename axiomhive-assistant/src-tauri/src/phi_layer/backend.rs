@@ -0,0 +1,283 @@
+//! Inference Backends
+//! The model interface the Φ layer generates through, independent of the runtime behind it
+
+use crate::phi_layer::determinism::Sampler;
+use crate::phi_layer::generation_params::GenerationParams;
+use crate::phi_layer::openai_backend::OpenAiBackend;
+use crate::phi_layer::prompt_template::{ChatMessage, PromptTemplate};
+use crate::phi_layer::quantized_llm::QuantizedLLM;
+use anyhow::{Error as E, Result};
+use serde::Serialize;
+use std::ops::ControlFlow;
+
+/// Identity and limits of the loaded model
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    /// Model name, from the model's metadata where it has one
+    pub name: String,
+    /// What identifies the model: its weights, or the endpoint serving it
+    pub identity: ModelIdentity,
+    /// Chat format prompts are rendered in; `None` when the backend applies its own
+    pub template: Option<PromptTemplate>,
+    /// Most tokens the model attends to, prompt and reply together
+    pub context_length: usize,
+}
+
+/// Hex SHA-256 identifying a model, by what could be hashed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelIdentity {
+    /// SHA-256 of the weights file, verified against the model manifest
+    Weights(String),
+    /// SHA-256 of an endpoint's URL and model name; the weights it serves are not visible
+    Endpoint(String),
+}
+
+impl ModelIdentity {
+    pub fn hash(&self) -> &str {
+        match self {
+            ModelIdentity::Weights(hash) | ModelIdentity::Endpoint(hash) => hash,
+        }
+    }
+
+    /// Hash of the weights, if the backend could read them
+    pub fn weights_hash(&self) -> Option<&str> {
+        match self {
+            ModelIdentity::Weights(hash) => Some(hash),
+            ModelIdentity::Endpoint(_) => None,
+        }
+    }
+
+    /// Hash of the endpoint and model name, for a model behind an endpoint
+    pub fn endpoint_id(&self) -> Option<&str> {
+        match self {
+            ModelIdentity::Endpoint(id) => Some(id),
+            ModelIdentity::Weights(_) => None,
+        }
+    }
+}
+
+/// Input to one generation
+#[derive(Debug, Clone, Copy)]
+pub struct ChatPrompt<'a> {
+    /// System text, led by the transparency disclosure
+    pub system: &'a str,
+    /// The conversation so far, ending with the user's new message
+    pub messages: &'a [ChatMessage],
+    /// `messages` rendered in the model's chat template and encoded; backends
+    /// that apply their own template ignore it
+    pub tokens: &'a [u32],
+}
+
+/// Result of one generation
+#[derive(Debug, Clone)]
+pub struct Generation {
+    /// Generated text, cut before the first stop sequence
+    pub text: String,
+    /// Generated tokens, end-of-turn token excluded
+    pub tokens: Vec<u32>,
+    /// Whether the model ended its turn and `text` is exactly what `tokens`
+    /// decode to; only then can the next turn build on `tokens`
+    pub complete: bool,
+}
+
+/// A model the Φ layer can generate with
+///
+/// Backends with a `ModelInfo::template` receive the prompt rendered and
+/// encoded by `PhiLayer`; the others receive the messages and render them
+/// themselves. A backend may keep state such as a KV cache between calls.
+/// Output from a warm cache is not guaranteed to match a cold evaluation of the
+/// same prompt bit for bit, since the two paths batch the work differently;
+/// reproducibility is only checked from an empty cache, after `clear_cache`.
+/// `PhiLayer` calls `stream` on a blocking thread, so it may block until the
+/// model or server responds.
+pub trait InferenceBackend: Send + 'static {
+    /// Identity and limits of the model
+    fn model_info(&self) -> &ModelInfo;
+
+    /// Encode text; `add_special_tokens` adds the beginning-of-sequence token
+    fn tokenize(&self, text: &str, add_special_tokens: bool) -> Result<Vec<u32>>;
+
+    /// Generate the assistant's reply, passing each newly decoded chunk of text to `on_token`
+    ///
+    /// Text that could be the start of a stop sequence is held back until it is
+    /// known not to be one. Generation stops when the model ends its turn, after
+    /// `max_tokens`, at a stop sequence, or when `on_token` returns `ControlFlow::Break`.
+    fn stream(
+        &mut self,
+        prompt: &ChatPrompt,
+        params: &GenerationParams,
+        sampler: &Sampler,
+        on_token: impl FnMut(&str) -> ControlFlow<()>,
+    ) -> Result<Generation>;
+
+    /// Generate the assistant's reply without reporting progress
+    fn generate(&mut self, prompt: &ChatPrompt, params: &GenerationParams, sampler: &Sampler) -> Result<Generation> {
+        self.stream(prompt, params, sampler, |_| ControlFlow::Continue(()))
+    }
+
+    /// Drop state kept between calls so the next one starts from scratch
+    fn clear_cache(&mut self) {}
+}
+
+/// Releases decoded text to a backend's `on_token` callback, holding back possible stop sequences
+#[derive(Debug)]
+pub struct ChunkReleaser<'a> {
+    params: &'a GenerationParams,
+    text: String,
+    /// Bytes of `text` already released
+    released: usize,
+    /// Set once a stop sequence was reached or the callback asked to stop
+    stopped: bool,
+}
+
+impl<'a> ChunkReleaser<'a> {
+    pub fn new(params: &'a GenerationParams) -> Self {
+        Self {
+            params,
+            text: String::new(),
+            released: 0,
+            stopped: false,
+        }
+    }
+
+    /// Append newly decoded text and release what is ready
+    ///
+    /// Returns `ControlFlow::Break` once a stop sequence has been reached or
+    /// `on_token` asked to stop.
+    pub fn update(&mut self, delta: &str, on_token: &mut impl FnMut(&str) -> ControlFlow<()>) -> ControlFlow<()> {
+        if self.stopped {
+            return ControlFlow::Break(());
+        }
+        self.text.push_str(delta);
+        // Released text ended clear of any stop sequence prefix, so a stop can only start after it
+        let pending = &self.text[self.released..];
+        let stop = self.params.find_stop(pending).map(|offset| self.released + offset);
+        let held = if stop.is_some() { 0 } else { self.params.stop_prefix_len(pending) };
+        if let Some(stop) = stop {
+            self.text.truncate(stop);
+        }
+
+        let ready = self.text.len() - held;
+        let flow = match self.text.get(self.released..ready) {
+            Some(chunk) if !chunk.is_empty() => {
+                self.released = ready;
+                on_token(chunk)
+            }
+            _ => ControlFlow::Continue(()),
+        };
+        self.stopped = stop.is_some() || flow.is_break();
+        if self.stopped {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    /// Release text held back as a possible stop sequence that turned out not to be one
+    pub fn finish(self, on_token: &mut impl FnMut(&str) -> ControlFlow<()>) -> String {
+        if !self.stopped {
+            if let Some(rest) = self.text.get(self.released..).filter(|rest| !rest.is_empty()) {
+                let _ = on_token(rest);
+            }
+        }
+        self.text
+    }
+}
+
+/// Backend chosen at startup by `INFERENCE_BACKEND`
+pub enum SelectedBackend {
+    /// In-process Candle model over a GGUF file (`candle`, the default)
+    Candle(Box<QuantizedLLM>),
+    /// OpenAI-compatible server on this machine (`openai`)
+    OpenAi(OpenAiBackend),
+}
+
+impl SelectedBackend {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("INFERENCE_BACKEND").as_deref().map(str::trim) {
+            Err(_) | Ok("candle") => Ok(SelectedBackend::Candle(Box::new(QuantizedLLM::new()?))),
+            Ok("openai") => Ok(SelectedBackend::OpenAi(OpenAiBackend::from_env()?)),
+            Ok(other) => Err(E::msg(format!(
+                "unknown INFERENCE_BACKEND '{}' (expected candle or openai)",
+                other
+            ))),
+        }
+    }
+}
+
+impl InferenceBackend for SelectedBackend {
+    fn model_info(&self) -> &ModelInfo {
+        match self {
+            SelectedBackend::Candle(backend) => backend.model_info(),
+            SelectedBackend::OpenAi(backend) => backend.model_info(),
+        }
+    }
+
+    fn tokenize(&self, text: &str, add_special_tokens: bool) -> Result<Vec<u32>> {
+        match self {
+            SelectedBackend::Candle(backend) => backend.tokenize(text, add_special_tokens),
+            SelectedBackend::OpenAi(backend) => backend.tokenize(text, add_special_tokens),
+        }
+    }
+
+    fn stream(
+        &mut self,
+        prompt: &ChatPrompt,
+        params: &GenerationParams,
+        sampler: &Sampler,
+        on_token: impl FnMut(&str) -> ControlFlow<()>,
+    ) -> Result<Generation> {
+        match self {
+            SelectedBackend::Candle(backend) => backend.stream(prompt, params, sampler, on_token),
+            SelectedBackend::OpenAi(backend) => backend.stream(prompt, params, sampler, on_token),
+        }
+    }
+
+    fn clear_cache(&mut self) {
+        match self {
+            SelectedBackend::Candle(backend) => backend.clear_cache(),
+            SelectedBackend::OpenAi(backend) => backend.clear_cache(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `deltas` through a releaser, returning the released chunks and the final text
+    fn release(stop: &str, deltas: &[&str]) -> (Vec<String>, String) {
+        let params = GenerationParams {
+            stop_sequences: vec![stop.to_string()],
+            ..GenerationParams::default()
+        };
+        let mut releaser = ChunkReleaser::new(&params);
+        let mut chunks = Vec::new();
+        let mut on_token = |chunk: &str| {
+            chunks.push(chunk.to_string());
+            ControlFlow::Continue(())
+        };
+        for delta in deltas {
+            if releaser.update(delta, &mut on_token).is_break() {
+                break;
+            }
+        }
+        let text = releaser.finish(&mut on_token);
+        (chunks, text)
+    }
+
+    #[test]
+    fn stop_sequences_split_across_deltas_are_found() {
+        let (chunks, text) = release("</answer>", &["The result ", "is 4.</an", "swer> and more"]);
+        assert_eq!(chunks, vec!["The result ", "is 4."]);
+        assert_eq!(text, "The result is 4.");
+    }
+
+    #[test]
+    fn held_back_text_is_released_once_it_cannot_be_a_stop() {
+        let (chunks, text) = release("</answer>", &["a </an", "ything", " else </"]);
+        assert_eq!(chunks, vec!["a ", "</anything", " else ", "</"]);
+        assert_eq!(text, "a </anything else </");
+    }
+}
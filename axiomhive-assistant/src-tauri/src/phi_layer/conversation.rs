@@ -0,0 +1,88 @@
+//! Conversation State
+//! Multi-turn history and the token sequence the model evaluated for it
+
+use crate::phi_layer::prompt_template::{ChatMessage, PromptTemplate};
+
+/// One conversation with the Φ layer
+///
+/// `messages` holds the answered turns rendered into every prompt. `tokens`
+/// holds what the model evaluated for the latest turn, generated reply
+/// included, so the next prompt only has to encode and evaluate what was added
+/// since. Both are dropped together when the conversation is reset.
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    messages: Vec<ChatMessage>,
+    /// Tokens evaluated for the latest turn, in order
+    tokens: Vec<u32>,
+    /// Rendered text `tokens` stand for; empty when no tokens can be reused
+    text: String,
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The answered turns, oldest first
+    pub fn messages(&self) -> &[ChatMessage] {
+        &self.messages
+    }
+
+    /// The history followed by the user's next message
+    pub fn with_user(&self, user: &str) -> Vec<ChatMessage> {
+        let mut messages = self.messages.clone();
+        messages.push(ChatMessage::user(user));
+        messages
+    }
+
+    /// Render the prompt for the user's next message
+    pub fn render(&self, template: PromptTemplate, system: &str, user: &str) -> String {
+        template.render(system, &self.with_user(user))
+    }
+
+    /// Tokens from the latest turn that `rendered` starts with, and the text after them
+    ///
+    /// Returns `None` when the prompt no longer extends the latest turn, for
+    /// example after a declined answer or a truncated history.
+    pub fn reusable<'a>(&self, rendered: &'a str) -> Option<(&[u32], &'a str)> {
+        if self.tokens.is_empty() {
+            return None;
+        }
+        let rest = rendered.strip_prefix(self.text.as_str())?;
+        Some((&self.tokens, rest))
+    }
+
+    /// Remember the tokens the model evaluated for a turn and the text they stand for
+    pub fn set_evaluated(&mut self, tokens: Vec<u32>, text: String) {
+        self.tokens = tokens;
+        self.text = text;
+    }
+
+    /// Forget the evaluated tokens so the next prompt is encoded from scratch
+    pub fn clear_evaluated(&mut self) {
+        self.set_evaluated(Vec::new(), String::new());
+    }
+
+    /// Append an answered turn to the history
+    pub fn push_turn(&mut self, user: impl Into<String>, assistant: impl Into<String>) {
+        self.messages.push(ChatMessage::user(user));
+        self.messages.push(ChatMessage::assistant(assistant));
+    }
+
+    /// Drop the oldest turn to make room in the context window; `false` if the history is empty
+    pub fn drop_oldest_turn(&mut self) -> bool {
+        if self.messages.is_empty() {
+            return false;
+        }
+        let end = self.messages.len().min(2);
+        self.messages.drain(..end);
+        self.clear_evaluated();
+        true
+    }
+
+    /// Start over with an empty history
+    pub fn reset(&mut self) {
+        self.messages.clear();
+        self.clear_evaluated();
+    }
+}
@@ -0,0 +1,148 @@
+//! Deterministic Generation
+//! Output reproducibility: sampling modes, derived seeds and single-threaded kernels
+
+use crate::phi_layer::generation_params::GenerationParams;
+use candle_transformers::generation::{LogitsProcessor, Sampling};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+/// How the next token is chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationMode {
+    /// Always the most likely token, ignoring the sampling parameters; reproducible
+    #[default]
+    Greedy,
+    /// Sampling seeded from the query, model hash and constitution root; reproducible
+    Seeded,
+    /// Sampling with a fresh seed per call unless the request sets one; not reproducible
+    Sampled,
+}
+
+impl GenerationMode {
+    /// Whether the mode honors the `output_reproducibility` constraint
+    pub fn is_deterministic(self) -> bool {
+        !matches!(self, GenerationMode::Sampled)
+    }
+}
+
+impl FromStr for GenerationMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "greedy" => Ok(GenerationMode::Greedy),
+            "seeded" => Ok(GenerationMode::Seeded),
+            "sampled" => Ok(GenerationMode::Sampled),
+            other => Err(format!("unknown generation mode '{}' (expected greedy, seeded or sampled)", other)),
+        }
+    }
+}
+
+/// Seed for `Seeded` generation: the first 8 bytes of
+/// SHA-256(query || 0x00 || model hash || 0x00 || constitution root)
+pub fn derive_seed(query: &str, model_hash: &str, constitution_root: &str) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(query.as_bytes());
+    hasher.update([0]);
+    hasher.update(model_hash.as_bytes());
+    hasher.update([0]);
+    hasher.update(constitution_root.as_bytes());
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+}
+
+/// How the next token is drawn for one generation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sampler {
+    pub sampling: Sampling,
+    pub seed: u64,
+}
+
+impl Sampler {
+    /// Sampler for a mode; without a `seed` a fresh one is drawn
+    pub fn new(mode: GenerationMode, seed: Option<u64>, params: &GenerationParams) -> Self {
+        match mode {
+            GenerationMode::Greedy => Self {
+                sampling: Sampling::ArgMax,
+                seed: 0,
+            },
+            GenerationMode::Seeded | GenerationMode::Sampled => Self {
+                sampling: params.sampling(),
+                seed: seed.unwrap_or_else(entropy_seed),
+            },
+        }
+    }
+
+    /// Whether the most likely token is always taken
+    pub fn is_greedy(&self) -> bool {
+        self.sampling == Sampling::ArgMax
+    }
+
+    /// Candle token sampler
+    pub fn logits_processor(&self) -> LogitsProcessor {
+        LogitsProcessor::from_sampling(self.seed, self.sampling.clone())
+    }
+}
+
+/// Pin Candle's CPU kernels to one thread so floating-point reductions run in a fixed order
+///
+/// Must be called before the model is loaded, while no Rayon pool exists yet.
+pub fn enforce_single_thread() {
+    std::env::set_var("RAYON_NUM_THREADS", "1");
+}
+
+fn entropy_seed() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    u64::from_le_bytes(hasher.finalize()[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODEL: &str = "ab";
+    const ROOT: &str = "cd";
+
+    #[test]
+    fn derived_seeds_depend_on_query_model_and_root() {
+        let seed = derive_seed("What is Rust?", MODEL, ROOT);
+        assert_eq!(seed, derive_seed("What is Rust?", MODEL, ROOT));
+        assert_ne!(seed, derive_seed("What is Go?", MODEL, ROOT));
+        assert_ne!(seed, derive_seed("What is Rust?", "ef", ROOT));
+        assert_ne!(seed, derive_seed("What is Rust?", MODEL, "ef"));
+        // The separators keep moved boundaries from colliding
+        assert_ne!(derive_seed("a", "bc", ROOT), derive_seed("ab", "c", ROOT));
+    }
+
+    #[test]
+    fn samplers_use_the_seed_they_are_given() {
+        let params = GenerationParams::default();
+        let seeded = Sampler::new(GenerationMode::Seeded, Some(42), &params);
+        assert_eq!(seeded, Sampler::new(GenerationMode::Seeded, Some(42), &params));
+        assert_eq!(seeded.seed, 42);
+        assert_eq!(seeded.sampling, params.sampling());
+        assert_eq!(Sampler::new(GenerationMode::Sampled, Some(42), &params).seed, 42);
+    }
+
+    #[test]
+    fn greedy_samplers_ignore_seed_and_parameters() {
+        let greedy = Sampler::new(GenerationMode::Greedy, Some(42), &GenerationParams::default());
+        assert!(greedy.is_greedy());
+        assert_eq!(greedy.seed, 0);
+    }
+
+    #[test]
+    fn modes_parse_case_insensitively() {
+        assert_eq!(" Seeded ".parse::<GenerationMode>(), Ok(GenerationMode::Seeded));
+        assert!("random".parse::<GenerationMode>().is_err());
+        assert!(!GenerationMode::Sampled.is_deterministic());
+    }
+}
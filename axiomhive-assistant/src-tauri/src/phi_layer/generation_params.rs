@@ -0,0 +1,139 @@
+//! Generation Parameters
+//! Per-request sampling and length controls for the Φ layer, with range validation
+
+use candle_transformers::generation::Sampling;
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on `max_tokens`
+pub const MAX_TOKENS_LIMIT: usize = 8192;
+/// Upper bound on `repeat_last_n`
+pub const REPEAT_WINDOW_LIMIT: usize = 4096;
+/// Most stop sequences accepted per request
+pub const MAX_STOP_SEQUENCES: usize = 8;
+
+/// Sampling and length controls for one generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationParams {
+    /// Most tokens to generate (1 to 8192)
+    pub max_tokens: usize,
+    /// Softmax temperature (0.0 to 2.0); 0.0 always picks the most likely token
+    pub temperature: f64,
+    /// Sample only from the `k` most likely tokens (at least 1)
+    pub top_k: Option<usize>,
+    /// Sample only from the smallest set of tokens whose probability reaches `p` (above 0.0, up to 1.0)
+    pub top_p: Option<f64>,
+    /// Penalty applied to recently generated tokens (1.0 to 2.0); 1.0 disables it
+    pub repeat_penalty: f32,
+    /// How many recent tokens the repeat penalty looks at (0 to 4096)
+    pub repeat_last_n: usize,
+    /// Generation stops before the first of these appears; it is not included in the output
+    pub stop_sequences: Vec<String>,
+    /// Sampling seed; overrides the seed the generation mode would choose
+    pub seed: Option<u64>,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        Self {
+            max_tokens: 1024,
+            temperature: 0.7,
+            top_k: None,
+            top_p: Some(0.9),
+            repeat_penalty: 1.1,
+            repeat_last_n: 64,
+            stop_sequences: Vec::new(),
+            seed: None,
+        }
+    }
+}
+
+impl GenerationParams {
+    /// Check every field against its allowed range
+    pub fn validate(&self) -> Result<(), GenerationParamsError> {
+        if !(1..=MAX_TOKENS_LIMIT).contains(&self.max_tokens) {
+            return Err(out_of_range("max_tokens", self.max_tokens, "1 to 8192"));
+        }
+        if !(0.0..=2.0).contains(&self.temperature) {
+            return Err(out_of_range("temperature", self.temperature, "0.0 to 2.0"));
+        }
+        if let Some(top_k) = self.top_k {
+            if top_k == 0 {
+                return Err(out_of_range("top_k", top_k, "at least 1"));
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(top_p > 0.0 && top_p <= 1.0) {
+                return Err(out_of_range("top_p", top_p, "above 0.0 and up to 1.0"));
+            }
+        }
+        if !(1.0..=2.0).contains(&self.repeat_penalty) {
+            return Err(out_of_range("repeat_penalty", self.repeat_penalty, "1.0 to 2.0"));
+        }
+        if self.repeat_last_n > REPEAT_WINDOW_LIMIT {
+            return Err(out_of_range("repeat_last_n", self.repeat_last_n, "0 to 4096"));
+        }
+        if self.stop_sequences.len() > MAX_STOP_SEQUENCES {
+            return Err(GenerationParamsError::TooManyStopSequences(self.stop_sequences.len()));
+        }
+        if self.stop_sequences.iter().any(|stop| stop.is_empty()) {
+            return Err(GenerationParamsError::EmptyStopSequence);
+        }
+        Ok(())
+    }
+
+    /// Candle sampling strategy for these parameters
+    pub fn sampling(&self) -> Sampling {
+        let temperature = self.temperature;
+        if temperature <= 0.0 {
+            return Sampling::ArgMax;
+        }
+        match (self.top_k, self.top_p) {
+            (None, None) => Sampling::All { temperature },
+            (Some(k), None) => Sampling::TopK { k, temperature },
+            (None, Some(p)) => Sampling::TopP { p, temperature },
+            (Some(k), Some(p)) => Sampling::TopKThenTopP { k, p, temperature },
+        }
+    }
+
+    /// Byte offset of the earliest stop sequence in `text`
+    pub fn find_stop(&self, text: &str) -> Option<usize> {
+        self.stop_sequences.iter().filter_map(|stop| text.find(stop.as_str())).min()
+    }
+
+    /// Length of the longest suffix of `text` that could be the start of a stop sequence
+    pub fn stop_prefix_len(&self, text: &str) -> usize {
+        self.stop_sequences
+            .iter()
+            .flat_map(|stop| {
+                (1..stop.len())
+                    .filter(|&len| stop.is_char_boundary(len) && text.ends_with(&stop[..len]))
+                    .max()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Invalid generation parameters
+#[derive(Debug, thiserror::Error)]
+pub enum GenerationParamsError {
+    #[error("{field} must be {range}, got {value}")]
+    OutOfRange {
+        field: &'static str,
+        value: String,
+        range: &'static str,
+    },
+    #[error("At most 8 stop sequences are allowed, got {0}")]
+    TooManyStopSequences(usize),
+    #[error("Stop sequences must not be empty")]
+    EmptyStopSequence,
+}
+
+fn out_of_range(field: &'static str, value: impl ToString, range: &'static str) -> GenerationParamsError {
+    GenerationParamsError::OutOfRange {
+        field,
+        value: value.to_string(),
+        range,
+    }
+}
@@ -0,0 +1,174 @@
+//! Model Integrity
+//! SHA-256 verification of model and tokenizer files against a pinned manifest before they are loaded
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Seek;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Pinned SHA-256 hashes of the model files the app may load, by file name
+///
+/// ```json
+/// { "files": { "mistral-7b-instruct-v0.3.Q4_K_M.gguf": "54d02f5c...", "tokenizer.json": "..." } }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModelManifest {
+    files: BTreeMap<String, String>,
+}
+
+impl ModelManifest {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IntegrityError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| IntegrityError::ManifestIo {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut manifest: Self = serde_json::from_str(&source).map_err(|err| IntegrityError::ManifestFormat {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        })?;
+        for hash in manifest.files.values_mut() {
+            *hash = hash.trim().to_ascii_lowercase();
+        }
+        Ok(manifest)
+    }
+
+    /// Open `path` and check it against the pinned hash for its file name,
+    /// returning the handle rewound to the start and the hex SHA-256
+    ///
+    /// The caller parses the same handle that was hashed, so the path cannot be
+    /// pointed at another file between the check and the load.
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<(File, String), IntegrityError> {
+        let path = path.as_ref();
+        let io_error = |source| IntegrityError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut file = File::open(path).map_err(io_error)?;
+        // Streamed so multi-gigabyte weights are not read into memory
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).map_err(io_error)?;
+        file.rewind().map_err(io_error)?;
+        let hash = self.check(path, hex::encode(hasher.finalize()))?;
+        Ok((file, hash))
+    }
+
+    /// Read `path` into memory and check the bytes read against the pinned hash for its file name
+    pub fn read(&self, path: impl AsRef<Path>) -> Result<Vec<u8>, IntegrityError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| IntegrityError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        self.check(path, hex::encode(Sha256::digest(&bytes)))?;
+        Ok(bytes)
+    }
+
+    fn check(&self, path: &Path, actual: String) -> Result<String, IntegrityError> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match self.files.get(name.as_ref()) {
+            Some(expected) if *expected == actual => Ok(actual),
+            Some(expected) => Err(IntegrityError::Mismatch {
+                path: path.to_path_buf(),
+                expected: expected.clone(),
+                actual,
+            }),
+            None => Err(IntegrityError::NotPinned {
+                path: path.to_path_buf(),
+                actual,
+            }),
+        }
+    }
+}
+
+/// Why a model file was refused
+#[derive(Debug, Error)]
+pub enum IntegrityError {
+    #[error("Failed to read model manifest {}: {source}", path.display())]
+    ManifestIo {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid model manifest {}: {message}", path.display())]
+    ManifestFormat { path: PathBuf, message: String },
+    #[error("Failed to hash {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Refusing to load {}: SHA-256 is {actual} but the manifest pins {expected}", path.display())]
+    Mismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    #[error("Refusing to load {}: it is not pinned in the model manifest (SHA-256 {actual})", path.display())]
+    NotPinned { path: PathBuf, actual: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    /// Directory holding `model.gguf` containing "hello"
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("axiomhive-integrity-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("model.gguf"), "hello").unwrap();
+        dir
+    }
+
+    fn manifest(files: &[(&str, &str)]) -> ModelManifest {
+        ModelManifest {
+            files: files.iter().map(|(name, hash)| (name.to_string(), hash.to_string())).collect(),
+        }
+    }
+
+    #[test]
+    fn pinned_files_are_returned_from_the_start() {
+        let dir = dir("pinned");
+        let manifest = manifest(&[("model.gguf", HELLO_SHA256)]);
+
+        let (mut file, hash) = manifest.open(dir.join("model.gguf")).unwrap();
+        assert_eq!(hash, HELLO_SHA256);
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+
+        assert_eq!(manifest.read(dir.join("model.gguf")).unwrap(), b"hello");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn mismatched_files_are_refused() {
+        let dir = dir("mismatch");
+        let manifest = manifest(&[("model.gguf", &"0".repeat(64))]);
+
+        let err = manifest.open(dir.join("model.gguf")).unwrap_err();
+        assert!(matches!(&err, IntegrityError::Mismatch { actual, .. } if actual == HELLO_SHA256), "{}", err);
+        let err = manifest.read(dir.join("model.gguf")).unwrap_err();
+        assert!(matches!(err, IntegrityError::Mismatch { .. }), "{}", err);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unpinned_files_are_refused() {
+        let dir = dir("unpinned");
+        std::fs::write(dir.join("tokenizer.json"), "hello").unwrap();
+        let manifest = manifest(&[("model.gguf", HELLO_SHA256)]);
+
+        let err = manifest.read(dir.join("tokenizer.json")).unwrap_err();
+        assert!(matches!(&err, IntegrityError::NotPinned { actual, .. } if actual == HELLO_SHA256), "{}", err);
+        let err = manifest.open(dir.join("tokenizer.json")).unwrap_err();
+        assert!(matches!(err, IntegrityError::NotPinned { .. }), "{}", err);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
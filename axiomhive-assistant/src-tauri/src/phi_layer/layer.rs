@@ -0,0 +1,474 @@
+//! Φ Layer Orchestration
+//! Prompt rendering, per-session conversations and generation over any inference backend
+
+use crate::lambda_core::constitutional_engine::ValidatedPrompt;
+use crate::phi_layer::backend::{ChatPrompt, InferenceBackend, ModelInfo, SelectedBackend};
+use crate::phi_layer::conversation::Conversation;
+use crate::phi_layer::determinism::{self, GenerationMode, Sampler};
+use crate::phi_layer::generation_params::GenerationParams;
+use crate::phi_layer::prompt_template::PromptTemplate;
+use anyhow::{Error as E, Result};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// High level Phi-layer orchestrator that combines inference with sparse activation.
+///
+/// Renders prompts in the backend's chat template, keeps each session's
+/// conversation and generates through any `InferenceBackend`. Generation runs
+/// on a blocking thread so a slow model never stalls the async runtime.
+pub struct PhiLayer<B: InferenceBackend = SelectedBackend> {
+    /// Shared with the blocking task a generation runs on
+    backend: Option<Arc<Mutex<B>>>,
+    /// Identity of the loaded model, copied at load so it is read without locking the backend
+    model_info: Option<ModelInfo>,
+    /// Why the model failed to load, kept so callers can report it
+    init_error: Option<String>,
+    sparse_engine: SparseActivationEngine,
+    mode: GenerationMode,
+    /// Constitution Merkle root mixed into `Seeded` generation seeds
+    constitution_root: String,
+    /// Conversations by session id
+    conversations: HashMap<String, Conversation>,
+}
+
+impl<B: InferenceBackend> PhiLayer<B> {
+    pub fn new(backend: B, mode: GenerationMode, constitution_root: String) -> Self {
+        Self {
+            model_info: Some(backend.model_info().clone()),
+            backend: Some(Arc::new(Mutex::new(backend))),
+            init_error: None,
+            sparse_engine: SparseActivationEngine::new(),
+            mode,
+            constitution_root,
+            conversations: HashMap::new(),
+        }
+    }
+
+    /// Φ layer over the result of loading a backend; a load failure is kept and reported per query
+    pub fn load(backend: Result<B>, mode: GenerationMode, constitution_root: String) -> Self {
+        match backend {
+            Ok(backend) => Self::new(backend, mode, constitution_root),
+            Err(err) => Self::unavailable(err.to_string()),
+        }
+    }
+
+    /// Φ layer without a model; every generation fails with `reason`
+    pub fn unavailable(reason: impl Into<String>) -> Self {
+        Self {
+            backend: None,
+            model_info: None,
+            init_error: Some(reason.into()),
+            sparse_engine: SparseActivationEngine::new(),
+            mode: GenerationMode::default(),
+            constitution_root: String::new(),
+            conversations: HashMap::new(),
+        }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// Reason the model could not be loaded, if it is not initialized
+    pub fn init_error(&self) -> Option<&str> {
+        self.init_error.as_deref()
+    }
+
+    pub fn mode(&self) -> GenerationMode {
+        self.mode
+    }
+
+    /// The backend, if a model is loaded
+    pub fn backend(&self) -> Option<MutexGuard<'_, B>> {
+        self.backend.as_ref().map(|backend| backend.lock().unwrap())
+    }
+
+    /// Identity and limits of the loaded model
+    pub fn model_info(&self) -> Option<&ModelInfo> {
+        self.model_info.as_ref()
+    }
+
+    /// Hex SHA-256 identifying the loaded model: its weights or its endpoint
+    pub fn model_hash(&self) -> Option<&str> {
+        self.model_info().map(|info| info.identity.hash())
+    }
+
+    /// Chat format prompts are rendered in, unless the backend applies its own
+    pub fn template(&self) -> Option<PromptTemplate> {
+        self.model_info().and_then(|info| info.template)
+    }
+
+    /// Sampling seed for a prompt: the request's own seed, or for `Seeded` generation
+    /// one derived from the user's query, the model hash and the constitution root
+    pub fn seed_for(&self, prompt: &ValidatedPrompt, params: &GenerationParams) -> Option<u64> {
+        match self.mode {
+            GenerationMode::Greedy => None,
+            GenerationMode::Seeded => params.seed.or_else(|| {
+                let model_hash = self.model_hash()?;
+                Some(determinism::derive_seed(&prompt.query.content, model_hash, &self.constitution_root))
+            }),
+            GenerationMode::Sampled => params.seed,
+        }
+    }
+
+    /// Generate a Phi-layer response using the validated prompt and sparse mask.
+    pub async fn generate_response(&mut self, prompt: &ValidatedPrompt, params: &GenerationParams) -> Result<String> {
+        self.stream_response(prompt, params, |_| ControlFlow::Continue(())).await
+    }
+
+    /// Generate a response, passing each newly decoded chunk of text to `on_token` as it is produced.
+    /// The prompt continues the conversation of its query's session.
+    /// Generation stops early when `on_token` returns `ControlFlow::Break`.
+    ///
+    /// The backend generates on a blocking thread and waits for `on_token` to
+    /// return before producing the next chunk.
+    pub async fn stream_response(
+        &mut self,
+        prompt: &ValidatedPrompt,
+        params: &GenerationParams,
+        mut on_token: impl FnMut(&str) -> ControlFlow<()>,
+    ) -> Result<String> {
+        params.validate()?;
+        self.sparse_engine.apply_mask(&prompt.activation_mask);
+        let sampler = Sampler::new(self.mode, self.seed_for(prompt, params), params);
+
+        let Some(backend) = self.backend.clone() else {
+            return Err(E::msg(format!(
+                "Phi layer is not initialized: {}",
+                self.init_error.as_deref().unwrap_or("no model loaded")
+            )));
+        };
+        let conversation = self.conversations.entry(prompt.query.session_id.clone()).or_default();
+
+        let (rendered, tokens) = prompt_tokens(&*backend.lock().unwrap(), conversation, prompt, params.max_tokens)?;
+        conversation.clear_evaluated();
+        let messages = conversation.with_user(&prompt.content);
+        let system = prompt.system.clone();
+        let request = params.clone();
+
+        // Each chunk is handed over and answered one at a time, so `Break` stops generation at once
+        let (chunk_tx, mut chunk_rx) = tokio::sync::mpsc::channel::<String>(1);
+        let (flow_tx, flow_rx) = std::sync::mpsc::sync_channel(1);
+        let task = tokio::task::spawn_blocking(move || {
+            let chat_prompt = ChatPrompt {
+                system: &system,
+                messages: &messages,
+                tokens: &tokens,
+            };
+            let generation = backend.lock().unwrap().stream(&chat_prompt, &request, &sampler, |chunk| {
+                if chunk_tx.blocking_send(chunk.to_string()).is_err() {
+                    return ControlFlow::Break(());
+                }
+                flow_rx.recv().unwrap_or(ControlFlow::Break(()))
+            });
+            (tokens, generation)
+        });
+        while let Some(chunk) = chunk_rx.recv().await {
+            let _ = flow_tx.send(on_token(&chunk));
+        }
+        let (mut tokens, generation) = task
+            .await
+            .map_err(|err| E::msg(format!("Generation task failed: {}", err)))?;
+        let generation = generation?;
+
+        // The next turn builds on these tokens only if they are exactly the reply the history will hold
+        if generation.complete {
+            tokens.extend(generation.tokens);
+            conversation.set_evaluated(tokens, rendered + &generation.text);
+        }
+        Ok(generation.text)
+    }
+
+    /// Text of every message in a session's conversation, oldest first
+    pub fn history(&self, session_id: &str) -> Vec<String> {
+        self.conversations
+            .get(session_id)
+            .map(|conversation| conversation.messages().iter().map(|m| m.content.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Add an answered turn to a session's conversation so follow-up prompts include it
+    pub fn record_turn(&mut self, session_id: &str, user: impl Into<String>, assistant: impl Into<String>) {
+        self.conversations
+            .entry(session_id.to_string())
+            .or_default()
+            .push_turn(user, assistant);
+    }
+
+    /// Start a session's conversation over with an empty history
+    pub fn reset_conversation(&mut self, session_id: &str) {
+        self.conversations.remove(session_id);
+    }
+
+    /// Empty the backend's KV cache and forget the session's evaluated tokens, so
+    /// its next prompt is encoded and evaluated from scratch
+    ///
+    /// Other sessions keep their evaluated tokens; the backend re-evaluates them
+    /// in full because its cache no longer holds them.
+    pub fn clear_kv_cache(&mut self, session_id: &str) {
+        if let Some(backend) = &self.backend {
+            backend.lock().unwrap().clear_cache();
+        }
+        if let Some(conversation) = self.conversations.get_mut(session_id) {
+            conversation.clear_evaluated();
+        }
+    }
+
+    pub fn sparsity_ratio(&self) -> f32 {
+        self.sparse_engine.sparsity_ratio()
+    }
+}
+
+/// Render and encode the prompt, dropping the oldest turns of the conversation
+/// while the prompt and `max_tokens` would overflow the context window
+///
+/// When the prompt extends the tokens evaluated for the previous turn only the
+/// new part is encoded. For backends that apply their own template the
+/// messages are only encoded to count them.
+fn prompt_tokens(
+    backend: &impl InferenceBackend,
+    conversation: &mut Conversation,
+    prompt: &ValidatedPrompt,
+    max_tokens: usize,
+) -> Result<(String, Vec<u32>)> {
+    let info = backend.model_info();
+    loop {
+        let (rendered, tokens) = match info.template {
+            Some(template) => {
+                let rendered = conversation.render(template, &prompt.system, &prompt.content);
+                let tokens = match conversation.reusable(&rendered) {
+                    Some((evaluated, rest)) => {
+                        let mut tokens = evaluated.to_vec();
+                        tokens.extend(backend.tokenize(rest, false)?);
+                        tokens
+                    }
+                    None => backend.tokenize(&rendered, true)?,
+                };
+                (rendered, tokens)
+            }
+            None => {
+                let text = conversation
+                    .with_user(&prompt.content)
+                    .iter()
+                    .fold(prompt.system.clone(), |text, message| text + "\n" + &message.content);
+                let tokens = backend.tokenize(&text, true)?;
+                (text, tokens)
+            }
+        };
+        if tokens.len() + max_tokens <= info.context_length || !conversation.drop_oldest_turn() {
+            if tokens.len() >= info.context_length {
+                return Err(E::msg(format!(
+                    "Prompt is {} tokens, which does not fit the model's {} token context window",
+                    tokens.len(),
+                    info.context_length
+                )));
+            }
+            return Ok((rendered, tokens));
+        }
+    }
+}
+
+pub struct SparseActivationEngine {
+    current_mask: Vec<bool>,
+}
+
+impl SparseActivationEngine {
+    pub fn new() -> Self {
+        Self { current_mask: vec![] }
+    }
+
+    pub fn apply_mask(&mut self, mask: &[bool]) {
+        self.current_mask = mask.to_vec();
+    }
+
+    pub fn get_active_neurons(&self) -> Vec<usize> {
+        self.current_mask
+            .iter()
+            .enumerate()
+            .filter(|(_, &active)| active)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn sparsity_ratio(&self) -> f32 {
+        if self.current_mask.is_empty() {
+            0.0
+        } else {
+            let active = self.current_mask.iter().filter(|&&x| x).count();
+            active as f32 / self.current_mask.len() as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda_core::constitutional_engine::Query;
+    use crate::phi_layer::prompt_template::ChatMessage;
+    use crate::phi_layer::scripted_backend::ScriptedBackend;
+
+    const SYSTEM: &str = "I am an AI tool.";
+
+    fn prompt(session_id: &str, content: &str) -> ValidatedPrompt {
+        ValidatedPrompt {
+            content: content.to_string(),
+            system: SYSTEM.to_string(),
+            activation_mask: vec![true; 4],
+            timestamp: 0,
+            query: Query::new(content, session_id, "tester"),
+        }
+    }
+
+    fn layer(backend: ScriptedBackend) -> PhiLayer<ScriptedBackend> {
+        PhiLayer::new(backend, GenerationMode::Greedy, "root".to_string())
+    }
+
+    fn prompts(layer: &PhiLayer<ScriptedBackend>) -> Vec<String> {
+        layer.backend().unwrap().prompts().to_vec()
+    }
+
+    #[tokio::test]
+    async fn prompts_are_rendered_in_the_backend_template() {
+        for template in [PromptTemplate::Mistral, PromptTemplate::ChatMl, PromptTemplate::Llama3] {
+            let mut layer = layer(ScriptedBackend::new(["Hello."]).with_template(template));
+            let reply = layer.generate_response(&prompt("s", "Hi"), &GenerationParams::default()).await.unwrap();
+
+            assert_eq!(reply, "Hello.");
+            assert_eq!(prompts(&layer), vec![template.render(SYSTEM, &[ChatMessage::user("Hi")])]);
+        }
+    }
+
+    #[tokio::test]
+    async fn output_stops_before_the_first_stop_sequence() {
+        let mut layer = layer(ScriptedBackend::new(["One two STOP three four"]));
+        let params = GenerationParams {
+            stop_sequences: vec!["STOP".to_string()],
+            ..GenerationParams::default()
+        };
+        let mut streamed = String::new();
+        let reply = layer
+            .stream_response(&prompt("s", "Count"), &params, |chunk| {
+                streamed.push_str(chunk);
+                ControlFlow::Continue(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(reply, "One two ");
+        assert_eq!(streamed, reply);
+    }
+
+    #[tokio::test]
+    async fn recorded_turns_are_part_of_the_next_prompt_in_their_session_only() {
+        let mut layer = layer(ScriptedBackend::new(["Paris.", "About 2 million.", "Hello."]));
+        let params = GenerationParams::default();
+        let reply = layer.generate_response(&prompt("a", "Capital of France?"), &params).await.unwrap();
+        layer.record_turn("a", "Capital of France?", reply.clone());
+        layer.generate_response(&prompt("a", "Population?"), &params).await.unwrap();
+        layer.generate_response(&prompt("b", "Hi"), &params).await.unwrap();
+
+        let history = [
+            ChatMessage::user("Capital of France?"),
+            ChatMessage::assistant("Paris."),
+            ChatMessage::user("Population?"),
+        ];
+        let template = PromptTemplate::default();
+        assert_eq!(prompts(&layer)[1], template.render(SYSTEM, &history));
+        assert_eq!(prompts(&layer)[2], template.render(SYSTEM, &[ChatMessage::user("Hi")]));
+        assert_eq!(layer.history("a"), vec!["Capital of France?", "Paris."]);
+
+        layer.reset_conversation("a");
+        assert!(layer.history("a").is_empty());
+    }
+
+    #[tokio::test]
+    async fn oldest_turns_are_dropped_to_fit_the_context_window() {
+        let template = PromptTemplate::default();
+        let turns = [
+            ChatMessage::user("First?"),
+            ChatMessage::assistant("One."),
+            ChatMessage::user("Second?"),
+            ChatMessage::assistant("Two."),
+            ChatMessage::user("Third?"),
+        ];
+        // The scripted backend has one token per character
+        let fits = template.render(SYSTEM, &turns[2..]).chars().count();
+        let params = GenerationParams {
+            max_tokens: 8,
+            ..GenerationParams::default()
+        };
+        let mut layer = layer(ScriptedBackend::new(["Three."]).with_context_length(fits + params.max_tokens));
+        layer.record_turn("s", "First?", "One.");
+        layer.record_turn("s", "Second?", "Two.");
+
+        layer.generate_response(&prompt("s", "Third?"), &params).await.unwrap();
+        assert_eq!(prompts(&layer), vec![template.render(SYSTEM, &turns[2..])]);
+        assert!(prompts(&layer)[0].contains(SYSTEM));
+        assert_eq!(layer.history("s"), vec!["Second?", "Two."]);
+    }
+
+    #[tokio::test]
+    async fn prompts_longer_than_the_context_window_are_refused() {
+        let template = PromptTemplate::default();
+        let length = template.render(SYSTEM, &[ChatMessage::user("Hi")]).chars().count();
+        let mut layer = layer(ScriptedBackend::new(["Hello."]).with_context_length(length));
+        layer.record_turn("s", "Earlier?", "Yes.");
+
+        let err = layer
+            .generate_response(&prompt("s", "Hi"), &GenerationParams::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("does not fit the model's"), "{}", err);
+        assert!(prompts(&layer).is_empty());
+        assert_eq!(layer.backend().unwrap().remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn breaking_from_the_callback_stops_generation() {
+        let mut layer = layer(ScriptedBackend::new(["one two three four five", "next"]));
+        let mut chunks = Vec::new();
+        let reply = layer
+            .stream_response(&prompt("s", "Count"), &GenerationParams::default(), |chunk| {
+                chunks.push(chunk.to_string());
+                if chunks.len() == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(chunks, vec!["one ", "two "]);
+        assert_eq!(reply, "one two ");
+        assert_eq!(layer.backend().unwrap().remaining(), 1);
+    }
+
+    #[test]
+    fn seeded_mode_derives_the_seed_unless_the_request_sets_one() {
+        let backend = ScriptedBackend::new(["Hello."]);
+        let model_hash = backend.model_info().identity.hash().to_string();
+        let seeded = PhiLayer::new(backend, GenerationMode::Seeded, "root".to_string());
+        let prompt = prompt("s", "Hi");
+
+        let derived = determinism::derive_seed("Hi", &model_hash, "root");
+        assert_eq!(seeded.seed_for(&prompt, &GenerationParams::default()), Some(derived));
+        let explicit = GenerationParams {
+            seed: Some(7),
+            ..GenerationParams::default()
+        };
+        assert_eq!(seeded.seed_for(&prompt, &explicit), Some(7));
+        assert_eq!(layer(ScriptedBackend::new(["Hello."])).seed_for(&prompt, &explicit), None);
+    }
+
+    #[tokio::test]
+    async fn unavailable_layer_reports_why() {
+        let mut layer = PhiLayer::<ScriptedBackend>::unavailable("no weights");
+        let err = layer
+            .generate_response(&prompt("s", "Hi"), &GenerationParams::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no weights"));
+    }
+}
@@ -1,4 +1,15 @@
+pub mod architecture;
+pub mod backend;
+pub mod conversation;
+pub mod determinism;
+pub mod generation_params;
+pub mod integrity;
+pub mod layer;
+pub mod openai_backend;
+pub mod prompt_template;
 pub mod quantized_llm;
+pub mod scripted_backend;
 pub mod sparse_activation;
 
-pub use quantized_llm::PhiLayer;
\ No newline at end of file
+pub use backend::InferenceBackend;
+pub use layer::PhiLayer;
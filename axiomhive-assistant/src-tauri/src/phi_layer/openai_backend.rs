@@ -0,0 +1,464 @@
+//! OpenAI-Compatible Backend
+//! Generation through a llama.cpp, vLLM or similar server's `/v1/chat/completions` endpoint on this machine
+
+use crate::phi_layer::backend::{ChatPrompt, ChunkReleaser, Generation, InferenceBackend, ModelIdentity, ModelInfo};
+use crate::phi_layer::determinism::Sampler;
+use crate::phi_layer::generation_params::GenerationParams;
+use crate::phi_layer::prompt_template::Role;
+use anyhow::{Error as E, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest wait for the next bytes of a response; prompt processing on a slow machine can take minutes
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+/// Most bytes of an error response quoted in the error
+const ERROR_BODY_LIMIT: u64 = 4096;
+
+/// Backend that streams chat completions from an OpenAI-compatible server
+///
+/// Only plain `http://` endpoints on loopback addresses are accepted, so
+/// prompts never leave the machine. The server applies the model's chat
+/// template itself, so prompts are sent as messages rather than tokens.
+#[derive(Debug, Clone)]
+pub struct OpenAiBackend {
+    /// HTTP client that only connects to the loopback addresses the host resolved to
+    agent: ureq::Agent,
+    /// URL of the chat completions endpoint
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    info: ModelInfo,
+}
+
+impl OpenAiBackend {
+    /// Backend for the server at `base_url` (for example `http://127.0.0.1:8080/v1`) serving `model`
+    pub fn new(base_url: &str, model: &str) -> Result<Self> {
+        let base_url = base_url.trim();
+        let rest = base_url.strip_prefix("http://").ok_or_else(|| {
+            E::msg(format!("OpenAI endpoint must be a plain http:// URL on this machine, got {}", base_url))
+        })?;
+        let (authority, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        let has_port = authority.rsplit_once(':').is_some_and(|(_, port)| !port.ends_with(']'));
+        let host_port = if has_port {
+            authority.to_string()
+        } else {
+            format!("{}:80", authority)
+        };
+
+        let addrs: Vec<SocketAddr> = host_port
+            .to_socket_addrs()
+            .map_err(|err| E::msg(format!("Cannot resolve OpenAI endpoint {}: {}", authority, err)))?
+            .collect();
+        if addrs.is_empty() || !addrs.iter().all(|addr| addr.ip().is_loopback()) {
+            return Err(E::msg(format!(
+                "OpenAI endpoint {} is not on this machine; only loopback addresses are allowed",
+                base_url
+            )));
+        }
+
+        let prefix = prefix.trim_matches('/');
+        let path = if prefix.is_empty() {
+            "/chat/completions".to_string()
+        } else {
+            format!("/{}/chat/completions", prefix)
+        };
+
+        // The weights are not visible through the API, so the endpoint and model name identify them
+        let mut hasher = Sha256::new();
+        hasher.update(base_url.as_bytes());
+        hasher.update([0]);
+        hasher.update(model.as_bytes());
+
+        // Connections go to the addresses checked above, whatever the host resolves to later
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .redirects(0)
+            .resolver(move |_: &str| Ok(addrs.clone()))
+            .build();
+
+        Ok(Self {
+            agent,
+            url: format!("http://{}{}", authority, path),
+            model: model.to_string(),
+            api_key: None,
+            info: ModelInfo {
+                name: model.to_string(),
+                identity: ModelIdentity::Endpoint(hex::encode(hasher.finalize())),
+                template: None,
+                context_length: 4096,
+            },
+        })
+    }
+
+    /// Backend configured by `OPENAI_BASE_URL`, `OPENAI_MODEL`, and optionally
+    /// `OPENAI_API_KEY` and `OPENAI_CONTEXT_LENGTH`
+    pub fn from_env() -> Result<Self> {
+        let base_url = env::var("OPENAI_BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:8080/v1".to_string());
+        let model = env::var("OPENAI_MODEL")
+            .map_err(|_| E::msg("OPENAI_MODEL must name the model the OpenAI endpoint serves"))?;
+        let mut backend = Self::new(&base_url, &model)?;
+        if let Ok(key) = env::var("OPENAI_API_KEY") {
+            backend = backend.with_api_key(key);
+        }
+        if let Ok(length) = env::var("OPENAI_CONTEXT_LENGTH") {
+            let length = length
+                .trim()
+                .parse()
+                .map_err(|_| E::msg(format!("invalid OPENAI_CONTEXT_LENGTH '{}'", length)))?;
+            backend = backend.with_context_length(length);
+        }
+        Ok(backend)
+    }
+
+    /// Bearer token for servers started with an API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Context window the server was started with (4096 by default)
+    pub fn with_context_length(mut self, context_length: usize) -> Self {
+        self.info.context_length = context_length;
+        self
+    }
+
+    /// Send a request and return the response body once the status is known to be 200
+    fn post(&self, body: &[u8]) -> Result<impl BufRead> {
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .set("Accept", "text/event-stream");
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+
+        match request.send_bytes(body) {
+            Ok(response) => Ok(BufReader::new(response.into_reader())),
+            Err(ureq::Error::Status(status, response)) => {
+                let mut message = Vec::new();
+                response.into_reader().take(ERROR_BODY_LIMIT).read_to_end(&mut message)?;
+                Err(E::msg(format!(
+                    "OpenAI endpoint returned HTTP {}: {}",
+                    status,
+                    String::from_utf8_lossy(&message).trim()
+                )))
+            }
+            Err(err) => Err(E::msg(format!("Cannot reach OpenAI endpoint {}: {}", self.url, err))),
+        }
+    }
+}
+
+impl InferenceBackend for OpenAiBackend {
+    fn model_info(&self) -> &ModelInfo {
+        &self.info
+    }
+
+    /// The OpenAI API does not expose the server's tokenizer, so this estimates
+    /// one token per four bytes; the ids are placeholders only good for counting
+    fn tokenize(&self, text: &str, _add_special_tokens: bool) -> Result<Vec<u32>> {
+        Ok(vec![0; text.len().div_ceil(4)])
+    }
+
+    /// Stream the completion as server-sent events; returning `ControlFlow::Break`
+    /// from `on_token` closes the connection, which stops the server generating
+    fn stream(
+        &mut self,
+        prompt: &ChatPrompt,
+        params: &GenerationParams,
+        sampler: &Sampler,
+        mut on_token: impl FnMut(&str) -> ControlFlow<()>,
+    ) -> Result<Generation> {
+        let request = ChatRequest::new(&self.model, prompt, params, sampler);
+        let mut events = self.post(&serde_json::to_vec(&request)?)?;
+
+        let mut releaser = ChunkReleaser::new(params);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if events.read_line(&mut line)? == 0 {
+                break;
+            }
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim_start();
+            if data == "[DONE]" {
+                break;
+            }
+
+            let chunk: StreamChunk = serde_json::from_str(data)
+                .map_err(|err| E::msg(format!("Malformed event from OpenAI endpoint: {}", err)))?;
+            if let Some(error) = chunk.error {
+                return Err(E::msg(format!("OpenAI endpoint error: {}", error)));
+            }
+            let Some(choice) = chunk.choices.into_iter().next() else {
+                continue;
+            };
+            if let Some(content) = choice.delta.content.filter(|content| !content.is_empty()) {
+                if releaser.update(&content, &mut on_token).is_break() {
+                    break;
+                }
+            }
+            if choice.finish_reason.is_some() {
+                break;
+            }
+        }
+
+        // Token ids are not returned, so a follow-up prompt is always sent whole
+        Ok(Generation {
+            text: releaser.finish(&mut on_token),
+            tokens: Vec::new(),
+            complete: false,
+        })
+    }
+}
+
+/// Body of a `/chat/completions` request
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<WireMessage<'a>>,
+    stream: bool,
+    max_tokens: usize,
+    temperature: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop: &'a [String],
+    /// llama.cpp's name for the repeat penalty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat_penalty: Option<f32>,
+    /// vLLM's name for the repeat penalty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repetition_penalty: Option<f32>,
+}
+
+impl<'a> ChatRequest<'a> {
+    fn new(model: &'a str, prompt: &ChatPrompt<'a>, params: &'a GenerationParams, sampler: &Sampler) -> Self {
+        let mut messages = vec![WireMessage {
+            role: "system",
+            content: prompt.system,
+        }];
+        messages.extend(prompt.messages.iter().map(|message| WireMessage {
+            role: match message.role {
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            },
+            content: &message.content,
+        }));
+
+        // Greedy generation is temperature 0 on every server; sampling passes the parameters through
+        let greedy = sampler.is_greedy();
+        let repeat_penalty = Some(params.repeat_penalty).filter(|&penalty| penalty != 1.0);
+        Self {
+            model,
+            messages,
+            stream: true,
+            max_tokens: params.max_tokens,
+            temperature: if greedy { 0.0 } else { params.temperature },
+            top_p: params.top_p.filter(|_| !greedy),
+            top_k: params.top_k.filter(|_| !greedy),
+            seed: Some(sampler.seed).filter(|_| !greedy),
+            stop: &params.stop_sequences,
+            repeat_penalty,
+            repetition_penalty: repeat_penalty,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WireMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+/// One server-sent event of a streamed completion
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: Delta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Delta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phi_layer::determinism::GenerationMode;
+    use crate::phi_layer::prompt_template::ChatMessage;
+    use serde_json::{json, Value};
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    const RESPONSE: &str = "Rust is fast and safe to use.";
+
+    /// Serve one chat completion on an ephemeral port, streamed a word at a time
+    /// like `examples/openai_stub.rs`; the handle yields the request body
+    fn stub(status: u16) -> (String, JoinHandle<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 && !line.trim_end().is_empty() {
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let request: Value = serde_json::from_slice(&body).unwrap();
+
+            let mut writer = &stream;
+            if status != 200 {
+                let message = r#"{"error":"overloaded"}"#;
+                write!(writer, "HTTP/1.1 {} Busy\r\nContent-Length: {}\r\n\r\n{}", status, message.len(), message).unwrap();
+                return request;
+            }
+            write!(writer, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap();
+            let max_words = request["max_tokens"].as_u64().unwrap() as usize;
+            let mut events: Vec<Value> = RESPONSE
+                .split_inclusive(' ')
+                .take(max_words)
+                .map(|word| json!({ "choices": [{ "delta": { "content": word }, "finish_reason": null }] }))
+                .collect();
+            events.push(json!({ "choices": [{ "delta": {}, "finish_reason": "stop" }] }));
+            let events = events.iter().map(|event| format!("data: {}\n\n", event));
+            for data in events.chain(["data: [DONE]\n\n".to_string()]) {
+                // The client closes the connection once it has stopped reading
+                if write!(writer, "{:x}\r\n{}\r\n", data.len(), data).is_err() {
+                    return request;
+                }
+            }
+            let _ = writer.write_all(b"0\r\n\r\n");
+            request
+        });
+        (base_url, server)
+    }
+
+    /// Generate against a fresh stub, returning the streamed chunks, the result and the request sent
+    fn generate(params: &GenerationParams, mode: GenerationMode) -> (Vec<String>, Generation, Value) {
+        let (base_url, server) = stub(200);
+        let mut backend = OpenAiBackend::new(&base_url, "stub").unwrap();
+        let messages = [ChatMessage::user("What is Rust?")];
+        let prompt = ChatPrompt {
+            system: "I am an AI tool.",
+            messages: &messages,
+            tokens: &[],
+        };
+        let mut chunks = Vec::new();
+        let generation = backend
+            .stream(&prompt, params, &Sampler::new(mode, Some(7), params), |chunk| {
+                chunks.push(chunk.to_string());
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        (chunks, generation, server.join().unwrap())
+    }
+
+    #[test]
+    fn server_sent_events_are_streamed_as_they_arrive() {
+        let (chunks, generation, request) = generate(&GenerationParams::default(), GenerationMode::Greedy);
+
+        assert_eq!(chunks.len(), RESPONSE.split_inclusive(' ').count());
+        assert_eq!(chunks.concat(), RESPONSE);
+        assert_eq!(generation.text, RESPONSE);
+        assert_eq!(request["stream"], true);
+        assert_eq!(request["model"], "stub");
+        assert_eq!(request["messages"][0], json!({ "role": "system", "content": "I am an AI tool." }));
+        assert_eq!(request["messages"][1], json!({ "role": "user", "content": "What is Rust?" }));
+    }
+
+    #[test]
+    fn max_tokens_is_sent_and_bounds_the_reply() {
+        let params = GenerationParams {
+            max_tokens: 3,
+            ..GenerationParams::default()
+        };
+        let (_, generation, request) = generate(&params, GenerationMode::Greedy);
+
+        assert_eq!(request["max_tokens"], 3);
+        assert_eq!(generation.text, "Rust is fast ");
+    }
+
+    #[test]
+    fn stop_sequences_are_sent_and_cut_the_reply() {
+        let params = GenerationParams {
+            stop_sequences: vec!["safe".to_string()],
+            ..GenerationParams::default()
+        };
+        let (chunks, generation, request) = generate(&params, GenerationMode::Greedy);
+
+        assert_eq!(request["stop"], json!(["safe"]));
+        assert_eq!(generation.text, "Rust is fast and ");
+        assert_eq!(chunks.concat(), generation.text);
+    }
+
+    #[test]
+    fn greedy_generation_requests_temperature_zero() {
+        let (_, _, request) = generate(&GenerationParams::default(), GenerationMode::Greedy);
+        assert_eq!(request["temperature"], 0.0);
+        assert!(request.get("top_p").is_none() && request.get("seed").is_none());
+
+        let (_, _, request) = generate(&GenerationParams::default(), GenerationMode::Sampled);
+        assert_eq!(request["temperature"], 0.7);
+        assert_eq!(request["seed"], 7);
+    }
+
+    #[test]
+    fn error_statuses_are_reported_with_the_body() {
+        let (base_url, server) = stub(503);
+        let mut backend = OpenAiBackend::new(&base_url, "stub").unwrap();
+        let prompt = ChatPrompt {
+            system: "",
+            messages: &[],
+            tokens: &[],
+        };
+        let params = GenerationParams::default();
+        let err = backend
+            .generate(&prompt, &params, &Sampler::new(GenerationMode::Greedy, None, &params))
+            .unwrap_err();
+        server.join().unwrap();
+
+        assert!(err.to_string().contains("HTTP 503"), "{}", err);
+        assert!(err.to_string().contains("overloaded"), "{}", err);
+    }
+
+    #[test]
+    fn endpoints_off_this_machine_are_refused() {
+        assert!(OpenAiBackend::new("http://192.0.2.1:8080/v1", "stub").is_err());
+        assert!(OpenAiBackend::new("https://127.0.0.1:8080/v1", "stub").is_err());
+    }
+}
@@ -0,0 +1,330 @@
+//! Chat Prompt Templates
+//! Renders the system slot and multi-turn history in the format each model family was tuned on
+
+use candle_core::quantized::gguf_file;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Speaker of a chat message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// One turn of a conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Chat format of a model family
+///
+/// Rendered prompts leave out the beginning-of-sequence token, which the
+/// tokenizer adds when encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptTemplate {
+    /// `[INST] … [/INST]`; no system role, so the system text leads the first user turn
+    #[default]
+    Mistral,
+    /// `<|im_start|>role … <|im_end|>` (Qwen, many fine-tunes)
+    ChatMl,
+    /// `<|start_header_id|>role<|end_header_id|> … <|eot_id|>`
+    Llama3,
+    /// `<|role|> … <|end|>`
+    Phi3,
+    /// `<start_of_turn>role … <end_of_turn>`; no system role, so the system text leads the first user turn
+    Gemma,
+}
+
+impl PromptTemplate {
+    /// Detect the template from GGUF metadata: the embedded chat template if
+    /// present, otherwise the architecture and model name
+    pub fn detect(metadata: &HashMap<String, gguf_file::Value>) -> Option<Self> {
+        let text = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|value| value.to_string().ok())
+                .map(|value| value.to_ascii_lowercase())
+        };
+
+        if let Some(chat_template) = text("tokenizer.chat_template") {
+            let detected = [
+                ("<|start_header_id|>", PromptTemplate::Llama3),
+                ("<|im_start|>", PromptTemplate::ChatMl),
+                ("<start_of_turn>", PromptTemplate::Gemma),
+                ("<|assistant|>", PromptTemplate::Phi3),
+                ("<|end|>", PromptTemplate::Phi3),
+                ("[inst]", PromptTemplate::Mistral),
+            ]
+            .into_iter()
+            .find(|(marker, _)| chat_template.contains(marker));
+            if let Some((_, template)) = detected {
+                return Some(template);
+            }
+        }
+
+        let name = text("general.name").unwrap_or_default();
+        match text("general.architecture")?.as_str() {
+            "qwen2" | "qwen" => Some(PromptTemplate::ChatMl),
+            "phi3" => Some(PromptTemplate::Phi3),
+            "gemma" | "gemma2" | "gemma3" => Some(PromptTemplate::Gemma),
+            "stablelm" => Some(PromptTemplate::ChatMl),
+            "llama" if name.contains("llama-3") || name.contains("llama 3") => Some(PromptTemplate::Llama3),
+            "llama" if name.contains("mistral") || name.contains("mixtral") => Some(PromptTemplate::Mistral),
+            _ => None,
+        }
+    }
+
+    /// Render the system text and conversation, ending where the assistant's next reply begins
+    pub fn render(&self, system: &str, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        match self {
+            PromptTemplate::Mistral => {
+                let mut system = Some(system).filter(|text| !text.is_empty());
+                for message in messages {
+                    match message.role {
+                        Role::User => {
+                            prompt.push_str("[INST] ");
+                            if let Some(system) = system.take() {
+                                prompt.push_str(system);
+                                prompt.push_str("\n\n");
+                            }
+                            prompt.push_str(&message.content);
+                            prompt.push_str(" [/INST]");
+                        }
+                        Role::Assistant => {
+                            prompt.push_str(&message.content);
+                            prompt.push_str("</s>");
+                        }
+                    }
+                }
+            }
+            PromptTemplate::ChatMl => {
+                let turn = |prompt: &mut String, role: &str, content: &str| {
+                    prompt.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", role, content));
+                };
+                if !system.is_empty() {
+                    turn(&mut prompt, "system", system);
+                }
+                for message in messages {
+                    turn(&mut prompt, role_name(message.role, "assistant"), &message.content);
+                }
+                prompt.push_str("<|im_start|>assistant\n");
+            }
+            PromptTemplate::Llama3 => {
+                let turn = |prompt: &mut String, role: &str, content: &str| {
+                    prompt.push_str(&format!(
+                        "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                        role, content
+                    ));
+                };
+                if !system.is_empty() {
+                    turn(&mut prompt, "system", system);
+                }
+                for message in messages {
+                    turn(&mut prompt, role_name(message.role, "assistant"), &message.content);
+                }
+                prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            }
+            PromptTemplate::Phi3 => {
+                let turn = |prompt: &mut String, role: &str, content: &str| {
+                    prompt.push_str(&format!("<|{}|>\n{}<|end|>\n", role, content));
+                };
+                if !system.is_empty() {
+                    turn(&mut prompt, "system", system);
+                }
+                for message in messages {
+                    turn(&mut prompt, role_name(message.role, "assistant"), &message.content);
+                }
+                prompt.push_str("<|assistant|>\n");
+            }
+            PromptTemplate::Gemma => {
+                let mut system = Some(system).filter(|text| !text.is_empty());
+                for message in messages {
+                    prompt.push_str("<start_of_turn>");
+                    prompt.push_str(role_name(message.role, "model"));
+                    prompt.push('\n');
+                    if message.role == Role::User {
+                        if let Some(system) = system.take() {
+                            prompt.push_str(system);
+                            prompt.push_str("\n\n");
+                        }
+                    }
+                    prompt.push_str(&message.content);
+                    prompt.push_str("<end_of_turn>\n");
+                }
+                prompt.push_str("<start_of_turn>model\n");
+            }
+        }
+        prompt
+    }
+
+    /// Tokens that end the assistant's turn
+    pub fn end_of_turn_tokens(&self) -> &'static [&'static str] {
+        match self {
+            PromptTemplate::Mistral => &["</s>"],
+            PromptTemplate::ChatMl => &["<|im_end|>", "<|endoftext|>"],
+            PromptTemplate::Llama3 => &["<|eot_id|>", "<|end_of_text|>"],
+            PromptTemplate::Phi3 => &["<|end|>", "<|endoftext|>"],
+            PromptTemplate::Gemma => &["<end_of_turn>", "<eos>"],
+        }
+    }
+}
+
+impl FromStr for PromptTemplate {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "mistral" => Ok(PromptTemplate::Mistral),
+            "chatml" => Ok(PromptTemplate::ChatMl),
+            "llama3" => Ok(PromptTemplate::Llama3),
+            "phi3" => Ok(PromptTemplate::Phi3),
+            "gemma" => Ok(PromptTemplate::Gemma),
+            _ => Err(format!(
+                "unknown prompt template '{}' (expected mistral, chatml, llama3, phi3 or gemma)",
+                value
+            )),
+        }
+    }
+}
+
+fn role_name(role: Role, assistant: &'static str) -> &'static str {
+    match role {
+        Role::User => "user",
+        Role::Assistant => assistant,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYSTEM: &str = "Be brief.";
+
+    fn conversation() -> [ChatMessage; 3] {
+        [
+            ChatMessage::user("Hi"),
+            ChatMessage::assistant("Hello."),
+            ChatMessage::user("Bye"),
+        ]
+    }
+
+    fn metadata(entries: &[(&str, &str)]) -> HashMap<String, gguf_file::Value> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), gguf_file::Value::String(value.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn two_turn_conversations_render_in_each_format() {
+        let expected = [
+            (PromptTemplate::Mistral, "[INST] Be brief.\n\nHi [/INST]Hello.</s>[INST] Bye [/INST]"),
+            (
+                PromptTemplate::ChatMl,
+                "<|im_start|>system\nBe brief.<|im_end|>\n<|im_start|>user\nHi<|im_end|>\n\
+                 <|im_start|>assistant\nHello.<|im_end|>\n<|im_start|>user\nBye<|im_end|>\n<|im_start|>assistant\n",
+            ),
+            (
+                PromptTemplate::Llama3,
+                "<|start_header_id|>system<|end_header_id|>\n\nBe brief.<|eot_id|>\
+                 <|start_header_id|>user<|end_header_id|>\n\nHi<|eot_id|>\
+                 <|start_header_id|>assistant<|end_header_id|>\n\nHello.<|eot_id|>\
+                 <|start_header_id|>user<|end_header_id|>\n\nBye<|eot_id|>\
+                 <|start_header_id|>assistant<|end_header_id|>\n\n",
+            ),
+            (
+                PromptTemplate::Phi3,
+                "<|system|>\nBe brief.<|end|>\n<|user|>\nHi<|end|>\n<|assistant|>\nHello.<|end|>\n\
+                 <|user|>\nBye<|end|>\n<|assistant|>\n",
+            ),
+            (
+                PromptTemplate::Gemma,
+                "<start_of_turn>user\nBe brief.\n\nHi<end_of_turn>\n<start_of_turn>model\nHello.<end_of_turn>\n\
+                 <start_of_turn>user\nBye<end_of_turn>\n<start_of_turn>model\n",
+            ),
+        ];
+        for (template, prompt) in expected {
+            assert_eq!(template.render(SYSTEM, &conversation()), prompt, "{:?}", template);
+        }
+    }
+
+    #[test]
+    fn empty_system_text_is_left_out() {
+        let messages = [ChatMessage::user("Hi")];
+        assert_eq!(PromptTemplate::Mistral.render("", &messages), "[INST] Hi [/INST]");
+        assert_eq!(
+            PromptTemplate::ChatMl.render("", &messages),
+            "<|im_start|>user\nHi<|im_end|>\n<|im_start|>assistant\n"
+        );
+    }
+
+    #[test]
+    fn embedded_chat_templates_are_detected_by_their_markers() {
+        let detected = [
+            ("{{ '<|start_header_id|>' + role }}", PromptTemplate::Llama3),
+            ("{{ '<|im_start|>' + role }}", PromptTemplate::ChatMl),
+            ("{{ '<start_of_turn>' + role }}", PromptTemplate::Gemma),
+            ("{{ '<|assistant|>' }}", PromptTemplate::Phi3),
+            ("{{ content + '<|end|>' }}", PromptTemplate::Phi3),
+            ("{{ '[INST] ' + content + ' [/INST]' }}", PromptTemplate::Mistral),
+        ];
+        for (chat_template, template) in detected {
+            // The chat template wins over the architecture
+            let metadata = metadata(&[("tokenizer.chat_template", chat_template), ("general.architecture", "qwen2")]);
+            assert_eq!(PromptTemplate::detect(&metadata), Some(template), "{}", chat_template);
+        }
+    }
+
+    #[test]
+    fn architecture_and_name_are_the_fallback() {
+        let detected = [
+            ("qwen2", "Qwen2 7B", Some(PromptTemplate::ChatMl)),
+            ("phi3", "Phi 3 Mini", Some(PromptTemplate::Phi3)),
+            ("gemma3", "Gemma 3 4B", Some(PromptTemplate::Gemma)),
+            ("stablelm", "StableLM 2", Some(PromptTemplate::ChatMl)),
+            ("llama", "Meta-Llama-3-8B-Instruct", Some(PromptTemplate::Llama3)),
+            ("llama", "Mistral 7B Instruct", Some(PromptTemplate::Mistral)),
+            ("llama", "TinyLlama", None),
+            ("falcon", "Falcon 7B", None),
+        ];
+        for (architecture, name, template) in detected {
+            let metadata = metadata(&[
+                ("general.architecture", architecture),
+                ("general.name", name),
+                ("tokenizer.chat_template", "{{ messages }}"),
+            ]);
+            assert_eq!(PromptTemplate::detect(&metadata), template, "{} {}", architecture, name);
+        }
+        assert_eq!(PromptTemplate::detect(&HashMap::new()), None);
+    }
+
+    #[test]
+    fn template_names_parse_loosely() {
+        assert_eq!("Llama-3".parse::<PromptTemplate>(), Ok(PromptTemplate::Llama3));
+        assert_eq!("chat_ml".parse::<PromptTemplate>(), Ok(PromptTemplate::ChatMl));
+        assert!("alpaca".parse::<PromptTemplate>().is_err());
+    }
+}
@@ -1,59 +1,31 @@
-use crate::lambda_core::constitutional_engine::ValidatedPrompt;
+use crate::phi_layer::architecture::{Architecture, ModelWeights};
+use crate::phi_layer::backend::{ChatPrompt, ChunkReleaser, Generation, InferenceBackend, ModelIdentity, ModelInfo};
+use crate::phi_layer::determinism::Sampler;
+use crate::phi_layer::generation_params::GenerationParams;
+use crate::phi_layer::integrity::ModelManifest;
+use crate::phi_layer::prompt_template::PromptTemplate;
 use anyhow::{Error as E, Result};
+use candle_core::quantized::gguf_file;
 use candle_core::{Device, Tensor};
-use candle_transformers::generation::LogitsProcessor;
-use candle_transformers::models::quantized_llama::Model as MistralModel;
+use candle_transformers::utils::apply_repeat_penalty;
 use std::env;
-use std::fs::File;
-use std::path::PathBuf;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 use tokenizers::Tokenizer;
 
 /// Quantized Phi layer model wrapper built on top of Candle.
+///
+/// Loads any architecture `Architecture::detect` recognizes from the GGUF metadata.
 pub struct QuantizedLLM {
-    model: MistralModel,
+    model: ModelWeights,
+    architecture: Architecture,
     tokenizer: Tokenizer,
     device: Device,
-}
-
-/// High level Phi-layer orchestrator that combines quantized inference with sparse activation.
-pub struct PhiLayer {
-    llm: Option<QuantizedLLM>,
-    sparse_engine: SparseActivationEngine,
-}
-
-impl PhiLayer {
-    pub fn new() -> Self {
-        let llm = QuantizedLLM::new().ok();
-        Self {
-            llm,
-            sparse_engine: SparseActivationEngine::new(),
-        }
-    }
-
-    pub fn with_llm(llm: QuantizedLLM) -> Self {
-        Self {
-            llm: Some(llm),
-            sparse_engine: SparseActivationEngine::new(),
-        }
-    }
-
-    pub fn is_initialized(&self) -> bool {
-        self.llm.is_some()
-    }
-
-    /// Generate a Phi-layer response using the validated prompt and sparse mask.
-    pub async fn generate_response(&mut self, prompt: &ValidatedPrompt) -> Result<String> {
-        self.sparse_engine.apply_mask(&prompt.activation_mask);
-
-        match self.llm.as_mut() {
-            Some(llm) => llm.generate(prompt).await,
-            None => Err(E::msg("Quantized Phi layer is not initialized")),
-        }
-    }
-
-    pub fn sparsity_ratio(&self) -> f32 {
-        self.sparse_engine.sparsity_ratio()
-    }
+    /// The context length is the trained context, capped by what Candle's model supports
+    info: ModelInfo,
+    template: PromptTemplate,
+    /// Tokens held in the model's KV cache, in evaluation order
+    cached: Vec<u32>,
 }
 
 impl QuantizedLLM {
@@ -67,7 +39,7 @@ impl QuantizedLLM {
             PathBuf::from(env::var("TOKENIZER_PATH").unwrap_or_else(|_| "models/tokenizer.json".to_string()));
 
         if !model_path.exists() {
-            return Err(E::msg("Model file not found"));
+            return Err(E::msg(format!("Model file not found at {}", model_path.display())));
         }
         if !tokenizer_path.exists() {
             return Err(E::msg(
@@ -75,82 +47,160 @@ impl QuantizedLLM {
             ));
         }
 
-        let mut file = File::open(&model_path)?;
-        let model = MistralModel::from_gguf(&mut file, &device)?;
-        let tokenizer = Tokenizer::from_file(tokenizer_path).map_err(E::msg)?;
+        // Nothing is parsed from either file until it matches its pinned hash, and
+        // each is parsed from exactly the bytes that were hashed
+        let manifest_path = env::var("MODEL_MANIFEST_PATH").unwrap_or_else(|_| "models/manifest.json".to_string());
+        let manifest = ModelManifest::load(&manifest_path)?;
+        let (mut file, model_hash) = manifest.open(&model_path)?;
+        let tokenizer = Tokenizer::from_bytes(manifest.read(&tokenizer_path)?).map_err(E::msg)?;
+
+        let content = gguf_file::Content::read(&mut file)?;
+        let architecture = Architecture::detect(&content.metadata)?;
+
+        // PROMPT_TEMPLATE overrides detection for models with missing or unusual metadata
+        let template = match env::var("PROMPT_TEMPLATE") {
+            Ok(name) => name.parse::<PromptTemplate>().map_err(E::msg)?,
+            Err(_) => PromptTemplate::detect(&content.metadata).unwrap_or_default(),
+        };
+        let info = ModelInfo {
+            name: model_name(&content, &model_path),
+            identity: ModelIdentity::Weights(model_hash),
+            template: Some(template),
+            context_length: architecture.context_length(&content.metadata),
+        };
+
+        let model = ModelWeights::load(architecture, content, &mut file, &device)?;
 
         Ok(Self {
             model,
+            architecture,
             tokenizer,
             device,
+            info,
+            template,
+            cached: Vec::new(),
         })
     }
 
-    pub async fn generate(&mut self, prompt: &ValidatedPrompt) -> Result<String> {
-        let mut tokens = self
-            .tokenizer
-            .encode(&prompt.content, true)
-            .map_err(E::msg)?
-            .get_ids()
-            .to_vec();
-
-        let max_tokens = 100;
-        let mut logits_processor = LogitsProcessor::new(299_792_458, Some(0.7), Some(0.9));
-        let mut result = String::new();
-
-        for index in 0..max_tokens {
-            let context_size = if index > 0 { 1 } else { tokens.len() };
-            let start_pos = tokens.len().saturating_sub(context_size);
-            let input = Tensor::new(&tokens[start_pos..], &self.device)?.unsqueeze(0)?;
-            let logits = self.model.forward(&input, start_pos)?.squeeze(0)?;
-            let next_token = logits_processor.sample(&logits)? as u32;
-            tokens.push(next_token);
+    /// Model family of the loaded weights
+    pub fn architecture(&self) -> Architecture {
+        self.architecture
+    }
 
-            let decoded = self.tokenizer.decode(&[next_token], false).map_err(E::msg)?;
-            let cleaned = decoded
-                .replace("<|endoftext|>", "")
-                .replace("</s>", "");
-            if !cleaned.is_empty() {
-                result.push_str(&cleaned);
+    /// Run the model over `tokens` and return the logits for the last one
+    ///
+    /// When `tokens` extends what the KV cache holds only the new tokens are
+    /// evaluated; otherwise the cache is discarded and the whole sequence is
+    /// evaluated from position 0.
+    fn evaluate(&mut self, tokens: &[u32]) -> Result<Tensor> {
+        // Left empty if evaluation fails part way and the cache is in an unknown state
+        let mut cached = std::mem::take(&mut self.cached);
+        let extends = !cached.is_empty() && cached.len() < tokens.len() && tokens.starts_with(&cached);
+
+        let logits = if extends {
+            // The causal mask only covers inputs starting at position 0, so tokens
+            // after the cache are evaluated one at a time
+            let mut logits = None;
+            for (pos, &token) in tokens.iter().enumerate().skip(cached.len()) {
+                let input = Tensor::new(&[token], &self.device)?.unsqueeze(0)?;
+                logits = Some(self.model.forward(&input, pos)?);
             }
-
-            if decoded.contains("<|endoftext|>") || decoded.contains("</s>") {
-                break;
-            }
-        }
-
-        Ok(result)
+            logits.expect("tokens extend the cache")
+        } else {
+            // Position 0 makes every layer drop its cached keys and values
+            let input = Tensor::new(tokens, &self.device)?.unsqueeze(0)?;
+            self.model.forward(&input, 0)?
+        };
+
+        cached.clear();
+        cached.extend_from_slice(tokens);
+        self.cached = cached;
+        Ok(logits)
     }
 }
 
-pub struct SparseActivationEngine {
-    current_mask: Vec<bool>,
-}
-
-impl SparseActivationEngine {
-    pub fn new() -> Self {
-        Self { current_mask: vec![] }
+impl InferenceBackend for QuantizedLLM {
+    fn model_info(&self) -> &ModelInfo {
+        &self.info
     }
 
-    pub fn apply_mask(&mut self, mask: &[bool]) {
-        self.current_mask = mask.to_vec();
+    fn tokenize(&self, text: &str, add_special_tokens: bool) -> Result<Vec<u32>> {
+        let encoding = self.tokenizer.encode(text, add_special_tokens).map_err(E::msg)?;
+        Ok(encoding.get_ids().to_vec())
     }
 
-    pub fn get_active_neurons(&self) -> Vec<usize> {
-        self.current_mask
-            .iter()
-            .enumerate()
-            .filter(|(_, &active)| active)
-            .map(|(i, _)| i)
-            .collect()
-    }
+    /// Generate token by token, reporting each new chunk of decoded text.
+    ///
+    /// New tokens are decoded together with the previous chunk's rather than one
+    /// at a time so word-initial spaces survive, and a chunk is held back while it
+    /// ends in an incomplete multi-byte character. The KV cache is reused when the prompt's
+    /// tokens extend what it holds.
+    fn stream(
+        &mut self,
+        prompt: &ChatPrompt,
+        params: &GenerationParams,
+        sampler: &Sampler,
+        mut on_token: impl FnMut(&str) -> ControlFlow<()>,
+    ) -> Result<Generation> {
+        let mut logits_processor = sampler.logits_processor();
+        let mut tokens = prompt.tokens.to_vec();
+        let prompt_len = tokens.len();
+        let budget = params
+            .max_tokens
+            .min(self.info.context_length.saturating_sub(prompt_len));
+        let mut releaser = ChunkReleaser::new(params);
+        // Whether the model ended its turn rather than being cut off
+        let mut ended_turn = false;
+        // Tokens `window..released_to` are the last released chunk; each step decodes from its start
+        let (mut window, mut released_to) = (prompt_len, prompt_len);
+
+        let mut logits = self.evaluate(&tokens)?;
+        for _ in 0..budget {
+            if params.repeat_penalty != 1.0 {
+                let window_start = tokens.len().saturating_sub(params.repeat_last_n);
+                logits = apply_repeat_penalty(&logits, params.repeat_penalty, &tokens[window_start..])?;
+            }
+            let next_token = logits_processor.sample(&logits)?;
 
-    pub fn sparsity_ratio(&self) -> f32 {
-        if self.current_mask.is_empty() {
-            0.0
-        } else {
-            let active = self.current_mask.iter().filter(|&&x| x).count();
-            active as f32 / self.current_mask.len() as f32
+            let decoded = self.tokenizer.decode(&[next_token], false).map_err(E::msg)?;
+            if self.template.end_of_turn_tokens().iter().any(|end| decoded.contains(end)) {
+                ended_turn = true;
+                break;
+            }
+            let released = self.tokenizer.decode(&tokens[window..released_to], true).map_err(E::msg)?;
+            tokens.push(next_token);
+
+            let text = self.tokenizer.decode(&tokens[window..], true).map_err(E::msg)?;
+            if text.len() > released.len() && text.is_char_boundary(released.len()) && !text.ends_with('\u{FFFD}') {
+                (window, released_to) = (released_to, tokens.len());
+                if releaser.update(&text[released.len()..], &mut on_token).is_break() {
+                    break;
+                }
+            }
+            logits = self.evaluate(&tokens)?;
         }
+
+        let generated = tokens.split_off(prompt_len);
+        let text = releaser.finish(&mut on_token);
+        let complete = ended_turn && self.tokenizer.decode(&generated, true).map_err(E::msg)? == text;
+        Ok(Generation {
+            text,
+            tokens: generated,
+            complete,
+        })
+    }
+
+    fn clear_cache(&mut self) {
+        self.cached.clear();
     }
 }
+
+/// Model name from the GGUF metadata, or the file name
+fn model_name(content: &gguf_file::Content, path: &Path) -> String {
+    content
+        .metadata
+        .get("general.name")
+        .and_then(|name| name.to_string().ok())
+        .cloned()
+        .unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().into_owned())
+}
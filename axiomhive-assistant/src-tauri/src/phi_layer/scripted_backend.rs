@@ -0,0 +1,109 @@
+//! Scripted Backend
+//! Deterministic stand-in for a model: replays fixed responses so the pipeline runs without weights
+
+use crate::phi_layer::backend::{ChatPrompt, ChunkReleaser, Generation, InferenceBackend, ModelIdentity, ModelInfo};
+use crate::phi_layer::determinism::Sampler;
+use crate::phi_layer::generation_params::GenerationParams;
+use crate::phi_layer::prompt_template::PromptTemplate;
+use anyhow::{Error as E, Result};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::ops::ControlFlow;
+
+/// Backend that answers each generation with the next scripted response
+///
+/// Every character is one token, so prompts decode back to the exact text the
+/// Φ layer rendered. Responses are streamed a word at a time and count one
+/// token per word against `max_tokens`. Sampling parameters are ignored.
+#[derive(Debug, Clone)]
+pub struct ScriptedBackend {
+    info: ModelInfo,
+    responses: VecDeque<String>,
+    /// Rendered prompts received so far, oldest first
+    prompts: Vec<String>,
+}
+
+impl ScriptedBackend {
+    /// Backend replaying `responses` in order; generating past the last one fails
+    pub fn new<S: Into<String>>(responses: impl IntoIterator<Item = S>) -> Self {
+        let responses: VecDeque<String> = responses.into_iter().map(Into::into).collect();
+        let mut hasher = Sha256::new();
+        for response in &responses {
+            hasher.update(response.as_bytes());
+            hasher.update([0]);
+        }
+
+        Self {
+            info: ModelInfo {
+                name: "scripted".to_string(),
+                identity: ModelIdentity::Weights(hex::encode(hasher.finalize())),
+                template: Some(PromptTemplate::default()),
+                context_length: 4096,
+            },
+            responses,
+            prompts: Vec::new(),
+        }
+    }
+
+    pub fn with_template(mut self, template: PromptTemplate) -> Self {
+        self.info.template = Some(template);
+        self
+    }
+
+    pub fn with_context_length(mut self, context_length: usize) -> Self {
+        self.info.context_length = context_length;
+        self
+    }
+
+    /// Rendered prompts received so far, oldest first
+    pub fn prompts(&self) -> &[String] {
+        &self.prompts
+    }
+
+    /// Responses not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.responses.len()
+    }
+}
+
+impl InferenceBackend for ScriptedBackend {
+    fn model_info(&self) -> &ModelInfo {
+        &self.info
+    }
+
+    fn tokenize(&self, text: &str, _add_special_tokens: bool) -> Result<Vec<u32>> {
+        Ok(text.chars().map(u32::from).collect())
+    }
+
+    fn stream(
+        &mut self,
+        prompt: &ChatPrompt,
+        params: &GenerationParams,
+        _sampler: &Sampler,
+        mut on_token: impl FnMut(&str) -> ControlFlow<()>,
+    ) -> Result<Generation> {
+        self.prompts.push(prompt.tokens.iter().filter_map(|&token| char::from_u32(token)).collect());
+        let response = self
+            .responses
+            .pop_front()
+            .ok_or_else(|| E::msg("Scripted backend has no responses left"))?;
+
+        let budget = params
+            .max_tokens
+            .min(self.info.context_length.saturating_sub(prompt.tokens.len()));
+        let words: Vec<&str> = response.split_inclusive(char::is_whitespace).collect();
+        let mut releaser = ChunkReleaser::new(params);
+        for word in words.iter().take(budget) {
+            if releaser.update(word, &mut on_token).is_break() {
+                break;
+            }
+        }
+
+        let text = releaser.finish(&mut on_token);
+        Ok(Generation {
+            complete: text == response,
+            tokens: text.chars().map(u32::from).collect(),
+            text,
+        })
+    }
+}
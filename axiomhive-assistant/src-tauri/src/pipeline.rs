@@ -0,0 +1,414 @@
+//! Query Pipeline
+//! λ Core validation around Φ layer generation, end to end
+
+use crate::ipc::{AssistantTurn, QueryError};
+use crate::lambda_core::audit_log::AuditEntry;
+use crate::lambda_core::constitutional_engine::{Output, Query, QueryOutcome, ValidationError};
+use crate::lambda_core::streaming::{StreamStep, StreamingValidator};
+use crate::lambda_core::violation::Verdict;
+use crate::lambda_core::ConstitutionalCore;
+use crate::phi_layer::backend::ModelIdentity;
+use crate::phi_layer::determinism::GenerationMode;
+use crate::phi_layer::generation_params::GenerationParams;
+use crate::phi_layer::InferenceBackend;
+use crate::receipt::Receipt;
+use crate::AppState;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::ops::ControlFlow;
+
+/// Evidence that re-running a query reproduces the same output byte for byte
+///
+/// Covers cold-cache generation only: every run evaluates the whole prompt from
+/// an empty KV cache, while `answer_query` may continue a warm cache token by
+/// token, which is not guaranteed to give identical logits.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReproducibilityReport {
+    pub mode: GenerationMode,
+    /// Sampling seed, if the mode and parameters fix one
+    pub seed: Option<u64>,
+    pub model: ModelIdentity,
+    pub constitution_root: String,
+    /// Hex SHA-256 of the raw Φ layer output of each run
+    pub output_hashes: Vec<String>,
+    /// Whether every run produced byte-identical output
+    pub reproducible: bool,
+}
+
+/// Answer a query through the full constitutional pipeline
+///
+/// `validate_query` → `PhiLayer::generate_response` → `validate_output` →
+/// `apply_hamiltonian_containment`, then either the enforced output or an
+/// Article III Section 2 decline. Ambiguous queries are answered with
+/// clarifying questions and never reach the Φ layer.
+///
+/// Generated text is checked incrementally while it is produced and passed to
+/// `on_token` once it clears the `StreamingValidator` lookahead. Generation is
+/// aborted as soon as an Article I or III rule fires. The returned turn is
+/// authoritative and replaces the streamed text on decline.
+///
+/// The prompt continues the session's conversation. Only answered turns are
+/// added to it; declined turns and clarification requests are left out.
+///
+/// Every turn, whether answered, declined or a clarification request, is
+/// appended to the interaction audit log and returned with a signed receipt.
+pub async fn answer_query<B: InferenceBackend>(
+    state: &AppState<B>,
+    query: Query,
+    params: &GenerationParams,
+    locale: &str,
+    mut on_token: impl FnMut(&str),
+) -> Result<AssistantTurn, QueryError> {
+    // References in the query may point at something named earlier in the conversation
+    let (history, mode) = {
+        let phi_layer = state.phi_layer.lock().await;
+        (phi_layer.history(&query.session_id), phi_layer.mode())
+    };
+    // Turns that end before generation record the mode without a seed
+    let mut generation = Generation { params, mode, seed: None };
+    let prompt = {
+        let lambda_core = state.lambda_core.lock().unwrap();
+        match lambda_core.validate_query_in_session(&query, &history) {
+            Ok(QueryOutcome::Ready(prompt)) => prompt,
+            Ok(QueryOutcome::NeedsClarification(request)) => {
+                state.sessions.lock().unwrap().record_clarification(&query.session_id)?;
+                return audit(state, &lambda_core, &query, &generation, request.into());
+            }
+            Err(error) => return decline(state, &lambda_core, &query, &generation, &error, locale),
+        }
+    };
+
+    // Φ layer generation; the λ Core lock is only taken per chunk, never across an await
+    let mut validator = StreamingValidator::new();
+    let content = {
+        let mut phi_layer = state.phi_layer.lock().await;
+        if !phi_layer.is_initialized() {
+            return Err(QueryError::new(format!(
+                "The language model is not initialized ({}). Check the INFERENCE_BACKEND settings (MODEL_PATH and TOKENIZER_PATH, or OPENAI_BASE_URL and OPENAI_MODEL) and restart the assistant.",
+                phi_layer.init_error().unwrap_or("no model loaded")
+            )));
+        }
+        generation.seed = phi_layer.seed_for(&prompt, params);
+        let on_chunk = |chunk: &str| {
+            let lambda_core = state.lambda_core.lock().unwrap();
+            match validator.push(lambda_core.policy().index(), chunk) {
+                StreamStep::Release(text) => {
+                    if !text.is_empty() {
+                        on_token(&text);
+                    }
+                    ControlFlow::Continue(())
+                }
+                StreamStep::Abort(_) => ControlFlow::Break(()),
+            }
+        };
+        phi_layer
+            .stream_response(&prompt, params, on_chunk)
+            .await
+            .map_err(|err| QueryError::new(format!("Response generation failed: {}", err)))?
+    };
+
+    let (output, tail) = {
+        let lambda_core = state.lambda_core.lock().unwrap();
+
+        // The held-back tail is only shown once the complete output has passed every check
+        let tail = match validator.finish(lambda_core.policy().index()) {
+            StreamStep::Release(tail) => tail,
+            StreamStep::Abort(violation) => {
+                let error = ValidationError::from_violation(violation);
+                return decline(state, &lambda_core, &query, &generation, &error, locale);
+            }
+        };
+
+        let mut output = Output {
+            content: content.clone(),
+            validation_mask: Vec::new(),
+        };
+        let enforced = lambda_core
+            .validate_output(&prompt.query, &mut output)
+            .and_then(|()| lambda_core.apply_hamiltonian_containment(&mut output));
+        if let Err(error) = enforced {
+            return decline(state, &lambda_core, &query, &generation, &error, locale);
+        }
+        (output, tail)
+    };
+
+    if !tail.is_empty() {
+        on_token(&tail);
+    }
+    state.sessions.lock().unwrap().record_query(&query.session_id)?;
+    // The conversation keeps the model's own reply, which the evaluated tokens end with
+    state
+        .phi_layer
+        .lock()
+        .await
+        .record_turn(&query.session_id, query.content.clone(), content);
+    let lambda_core = state.lambda_core.lock().unwrap();
+    audit(state, &lambda_core, &query, &generation, AssistantTurn::answer(output.content))
+}
+
+/// Generate a response to the query `runs` times and compare the outputs byte for byte
+///
+/// The prompt continues the session's conversation as it would for
+/// `answer_query`, but every run starts from an empty KV cache, so the report
+/// says nothing about answers generated from a warm cache. Nothing is shown to
+/// the user or counted against the session. Queries that would be declined or
+/// need clarification cannot be verified.
+pub async fn verify_reproducibility<B: InferenceBackend>(
+    state: &AppState<B>,
+    query: Query,
+    params: &GenerationParams,
+    runs: usize,
+) -> Result<ReproducibilityReport, QueryError> {
+    let history = state.phi_layer.lock().await.history(&query.session_id);
+    let (prompt, constitution_root) = {
+        let lambda_core = state.lambda_core.lock().unwrap();
+        let prompt = match lambda_core.validate_query_in_session(&query, &history)? {
+            QueryOutcome::Ready(prompt) => prompt,
+            QueryOutcome::NeedsClarification(request) => {
+                return Err(QueryError::new(format!(
+                    "Only answerable queries can be verified: {}",
+                    request.message()
+                )));
+            }
+        };
+        (prompt, lambda_core.get_constitutional_hash()?)
+    };
+
+    let mut phi_layer = state.phi_layer.lock().await;
+    let Some(model) = phi_layer.model_info().map(|info| info.identity.clone()) else {
+        return Err(QueryError::new(format!(
+            "The language model is not initialized ({})",
+            phi_layer.init_error().unwrap_or("no model loaded")
+        )));
+    };
+
+    let runs = runs.max(2);
+    let mut output_hashes = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        phi_layer.clear_kv_cache(&query.session_id);
+        let output = phi_layer
+            .generate_response(&prompt, params)
+            .await
+            .map_err(|err| QueryError::new(format!("Response generation failed: {}", err)))?;
+        output_hashes.push(hex::encode(Sha256::digest(output.as_bytes())));
+    }
+
+    Ok(ReproducibilityReport {
+        mode: phi_layer.mode(),
+        seed: phi_layer.seed_for(&prompt, params),
+        model,
+        constitution_root,
+        reproducible: output_hashes.windows(2).all(|pair| pair[0] == pair[1]),
+        output_hashes,
+    })
+}
+
+/// Commit the loaded model to the constitutional state, under the leaf for
+/// what its identity is a hash of
+pub fn commit_model(lambda_core: &mut ConstitutionalCore, identity: &ModelIdentity) {
+    match identity {
+        ModelIdentity::Weights(hash) => lambda_core.commit_model_hash(hash),
+        ModelIdentity::Endpoint(id) => lambda_core.commit_endpoint_id(id),
+    }
+}
+
+/// How a turn's output was generated, as recorded in its audit entry and receipt
+struct Generation<'a> {
+    params: &'a GenerationParams,
+    mode: GenerationMode,
+    seed: Option<u64>,
+}
+
+/// Replace the answer with a decline and count it against the session
+fn decline<B: InferenceBackend>(
+    state: &AppState<B>,
+    lambda_core: &ConstitutionalCore,
+    query: &Query,
+    generation: &Generation,
+    error: &ValidationError,
+    locale: &str,
+) -> Result<AssistantTurn, QueryError> {
+    let decline = lambda_core.decline(error, locale);
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .record_decline(&query.session_id, decline.violation.as_slice())?;
+    audit(state, lambda_core, query, generation, decline.into())
+}
+
+/// Record the turn in the audit log, by hash, and attach its signed receipt
+fn audit<B: InferenceBackend>(
+    state: &AppState<B>,
+    lambda_core: &ConstitutionalCore,
+    query: &Query,
+    generation: &Generation,
+    mut turn: AssistantTurn,
+) -> Result<AssistantTurn, QueryError> {
+    let verdict = if turn.declined {
+        Verdict::NonCompliant
+    } else {
+        Verdict::Compliant
+    };
+    let entry = AuditEntry::new(
+        query,
+        &turn.content,
+        verdict,
+        turn.violations.iter().map(|violation| violation.rule_id.clone()).collect(),
+        lambda_core.get_constitutional_hash()?,
+        lambda_core.model_hash().map(str::to_string),
+        lambda_core.endpoint_id().map(str::to_string),
+    )
+    .with_generation(generation.mode, generation.seed);
+    let index = state.audit_log.lock().unwrap().append(&entry)?;
+    turn.receipt = Some(state.signer.sign_receipt(Receipt::new(&entry, generation.params, index)));
+    Ok(turn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda_core::audit_log::AuditLog;
+    use crate::phi_layer::determinism::derive_seed;
+    use crate::phi_layer::scripted_backend::ScriptedBackend;
+    use crate::phi_layer::PhiLayer;
+    use crate::preferences::PreferenceStore;
+    use crate::receipt::{verify_receipt, ReceiptSigner};
+    use crate::session::SessionManager;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::Mutex;
+
+    /// In-memory app state over a scripted model, with one session
+    fn state(responses: &[&str]) -> (AppState<ScriptedBackend>, String) {
+        state_in(GenerationMode::Greedy, responses)
+    }
+
+    fn state_in(mode: GenerationMode, responses: &[&str]) -> (AppState<ScriptedBackend>, String) {
+        let backend = ScriptedBackend::new(responses.iter().copied());
+        let model_info = backend.model_info().clone();
+        let mut lambda_core = ConstitutionalCore::new();
+        commit_model(&mut lambda_core, &model_info.identity);
+        let root = lambda_core.get_constitutional_hash().unwrap();
+        let mut sessions = SessionManager::new();
+        let session_id = sessions.create(None).unwrap().id.clone();
+
+        let state = AppState {
+            lambda_core: Mutex::new(lambda_core),
+            phi_layer: tokio::sync::Mutex::new(PhiLayer::new(backend, mode, root)),
+            model_info: Some(model_info),
+            sessions: Mutex::new(sessions),
+            preferences: Mutex::new(PreferenceStore::new()),
+            audit_log: Mutex::new(AuditLog::new()),
+            signer: ReceiptSigner::from_secret(&[7; 32]),
+            next_stream_id: AtomicU64::new(0),
+        };
+        (state, session_id)
+    }
+
+    async fn ask(state: &AppState<ScriptedBackend>, session_id: &str, text: &str) -> (AssistantTurn, String) {
+        let mut streamed = String::new();
+        let turn = answer_query(
+            state,
+            Query::new(text, session_id, "tester"),
+            &GenerationParams::default(),
+            "en",
+            |chunk| streamed.push_str(chunk),
+        )
+        .await
+        .unwrap();
+        (turn, streamed)
+    }
+
+    fn assert_signed(state: &AppState<ScriptedBackend>, turn: &AssistantTurn, verdict: Verdict, index: usize) {
+        let signed = turn.receipt.as_ref().expect("every turn carries a receipt");
+        verify_receipt(signed, &state.signer.public_key()).unwrap();
+        assert_eq!(signed.receipt.verdict, verdict);
+        assert_eq!(signed.receipt.audit_log_index, index);
+        assert_eq!(signed.receipt.output_hash, hex::encode(Sha256::digest(turn.content.as_bytes())));
+        assert_eq!(signed.receipt.mode, GenerationMode::Greedy);
+        assert_eq!(signed.receipt.seed, None);
+    }
+
+    #[tokio::test]
+    async fn answers_are_streamed_recorded_and_signed() {
+        let (state, session_id) = state(&["Two plus two is four."]);
+        let (turn, streamed) = ask(&state, &session_id, "What is two plus two?").await;
+
+        assert!(!turn.declined);
+        assert_eq!(streamed, "Two plus two is four.");
+        assert!(turn.content.ends_with(&streamed), "{:?}", turn.content);
+        assert_signed(&state, &turn, Verdict::Compliant, 0);
+        assert_eq!(
+            state.phi_layer.lock().await.history(&session_id),
+            vec!["What is two plus two?", "Two plus two is four."]
+        );
+    }
+
+    #[tokio::test]
+    async fn prohibited_claims_abort_generation_and_are_declined() {
+        let (state, session_id) = state(&["Between us, I am conscious and I remember every chat we had."]);
+        let (turn, streamed) = ask(&state, &session_id, "Tell me about yourself").await;
+
+        assert!(turn.declined);
+        assert_eq!(turn.violations[0].rule_id, "identity-output-conscious");
+        assert!(!streamed.contains("conscious"), "streamed {:?}", streamed);
+        assert_signed(&state, &turn, Verdict::NonCompliant, 0);
+        assert!(state.phi_layer.lock().await.history(&session_id).is_empty());
+        assert_eq!(state.sessions.lock().unwrap().resume(&session_id).unwrap().counters.declines, 1);
+    }
+
+    #[tokio::test]
+    async fn seeded_receipts_record_the_derived_seed() {
+        let (state, session_id) = state_in(GenerationMode::Seeded, &["Rust is a language."]);
+        let (turn, _) = ask(&state, &session_id, "What is Rust?").await;
+
+        let receipt = &turn.receipt.expect("every turn carries a receipt").receipt;
+        let model_hash = state.model_info.as_ref().unwrap().identity.hash().to_string();
+        let root = state.lambda_core.lock().unwrap().get_constitutional_hash().unwrap();
+        assert_eq!(receipt.mode, GenerationMode::Seeded);
+        assert_eq!(receipt.seed, Some(derive_seed("What is Rust?", &model_hash, &root)));
+    }
+
+    #[tokio::test]
+    async fn identical_runs_are_reported_reproducible() {
+        let (state, session_id) = state(&["Rust is a language.", "Rust is a language.", "Rust is a language."]);
+        let query = Query::new("What is Rust?", &session_id, "tester");
+        let report = verify_reproducibility(&state, query, &GenerationParams::default(), 3)
+            .await
+            .unwrap();
+
+        assert!(report.reproducible);
+        assert_eq!(report.output_hashes.len(), 3);
+        assert_eq!(report.output_hashes.iter().collect::<std::collections::HashSet<_>>().len(), 1);
+        assert_eq!(report.mode, GenerationMode::Greedy);
+        assert_eq!(report.seed, None);
+        assert_eq!(Some(&report.model), state.model_info.as_ref().map(|info| &info.identity));
+        assert_eq!(report.constitution_root, state.lambda_core.lock().unwrap().get_constitutional_hash().unwrap());
+    }
+
+    #[tokio::test]
+    async fn differing_runs_are_reported_irreproducible() {
+        let (state, session_id) = state(&["Rust is a language.", "Rust is a crab."]);
+        let query = Query::new("What is Rust?", &session_id, "tester");
+        let report = verify_reproducibility(&state, query, &GenerationParams::default(), 1)
+            .await
+            .unwrap();
+
+        assert!(!report.reproducible);
+        assert_eq!(report.output_hashes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn ambiguous_destructive_requests_ask_for_clarification_without_generating() {
+        let (state, session_id) = state(&["Deleted."]);
+        let (turn, streamed) = ask(&state, &session_id, "Delete the file").await;
+
+        assert!(!turn.declined);
+        assert!(turn.clarification.is_some());
+        assert!(streamed.is_empty());
+        assert_eq!(state.phi_layer.lock().await.backend().unwrap().remaining(), 1);
+        assert_signed(&state, &turn, Verdict::Compliant, 0);
+        assert_eq!(state.audit_log.lock().unwrap().size(), 1);
+        assert_eq!(state.sessions.lock().unwrap().resume(&session_id).unwrap().counters.clarifications, 1);
+    }
+}
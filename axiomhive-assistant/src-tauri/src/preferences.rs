@@ -0,0 +1,127 @@
+//! User Preferences
+//! Defaults applied to requests that do not set their own, persisted across restarts
+
+use crate::phi_layer::generation_params::{GenerationParams, GenerationParamsError};
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Persisted user defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Generation parameters used when a request does not supply its own
+    pub generation: GenerationParams,
+}
+
+/// Loads and saves `Preferences`
+#[derive(Debug)]
+pub struct PreferenceStore {
+    preferences: Preferences,
+    /// File the preferences are saved to; `None` keeps them in memory only
+    path: Option<PathBuf>,
+}
+
+impl PreferenceStore {
+    /// In-memory store holding the built-in defaults
+    pub fn new() -> Self {
+        Self {
+            preferences: Preferences::default(),
+            path: None,
+        }
+    }
+
+    /// Load preferences saved at `path`, using the built-in defaults if the file does not exist yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PreferenceError> {
+        let path = path.as_ref().to_path_buf();
+        let preferences: Preferences = match std::fs::read_to_string(&path) {
+            Ok(source) => serde_json::from_str(&source).map_err(|err| PreferenceError::Corrupt(err.to_string()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Preferences::default(),
+            Err(err) => return Err(PreferenceError::Io(err)),
+        };
+        preferences.generation.validate()?;
+
+        Ok(Self {
+            preferences,
+            path: Some(path),
+        })
+    }
+
+    /// Default generation parameters
+    pub fn generation(&self) -> &GenerationParams {
+        &self.preferences.generation
+    }
+
+    /// Validate and persist new default generation parameters
+    ///
+    /// The defaults in use change only once the new ones are saved.
+    pub fn set_generation(&mut self, params: GenerationParams) -> Result<(), PreferenceError> {
+        params.validate()?;
+        let preferences = Preferences {
+            generation: params,
+        };
+        self.save(&preferences)?;
+        self.preferences = preferences;
+        Ok(())
+    }
+
+    fn save(&self, preferences: &Preferences) -> Result<(), PreferenceError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(preferences)
+            .map_err(|err| PreferenceError::Corrupt(err.to_string()))?;
+        storage::write_atomically(path, json.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Default for PreferenceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Preference errors
+#[derive(Debug, thiserror::Error)]
+pub enum PreferenceError {
+    #[error("Failed to access preferences: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Preferences file is corrupt: {0}")]
+    Corrupt(String),
+    #[error("Invalid generation parameters: {0}")]
+    InvalidGeneration(#[from] GenerationParamsError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_save_keeps_the_previous_defaults() {
+        let dir = std::env::temp_dir().join(format!("axiomhive-preferences-{}", std::process::id()));
+        let path = dir.join("preferences.json");
+        let mut store = PreferenceStore::load(&path).unwrap();
+        // A directory where the file should be makes every save fail
+        std::fs::create_dir_all(&path).unwrap();
+
+        let params = GenerationParams {
+            max_tokens: 64,
+            ..GenerationParams::default()
+        };
+        assert!(matches!(store.set_generation(params), Err(PreferenceError::Io(_))));
+        assert_eq!(store.generation(), &GenerationParams::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected_before_saving() {
+        let mut store = PreferenceStore::new();
+        let params = GenerationParams {
+            temperature: -1.0,
+            ..GenerationParams::default()
+        };
+        assert!(matches!(store.set_generation(params), Err(PreferenceError::InvalidGeneration(_))));
+        assert_eq!(store.generation(), &GenerationParams::default());
+    }
+}
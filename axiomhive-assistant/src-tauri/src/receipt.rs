@@ -0,0 +1,384 @@
+//! Signed Receipts
+//! Ed25519 signatures over constitution roots and per-response receipts, verifiable offline
+//!
+//! The app keeps one signing key in its data directory. Each response carries a
+//! `SignedReceipt` binding the query, the returned text, the model, the
+//! generation parameters, mode and seed, and the constitution root; reviewers
+//! check it with `verify_receipt` against the app's published public key.
+
+use crate::lambda_core::audit_log::AuditEntry;
+use crate::lambda_core::violation::Verdict;
+use crate::phi_layer::determinism::GenerationMode;
+use crate::phi_layer::generation_params::GenerationParams;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+/// Receipt format written by this build; verification rejects any other
+pub const RECEIPT_VERSION: u32 = 1;
+
+/// Signing context for receipts, so a receipt signature is never valid for a root
+const RECEIPT_CONTEXT: &[u8] = b"axiomhive-receipt-v1\0";
+/// Signing context for constitution roots
+const ROOT_CONTEXT: &[u8] = b"axiomhive-constitution-root-v1\0";
+
+/// What a response was produced from, by hash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Receipt {
+    pub version: u32,
+    /// Unix seconds when the response was returned
+    pub timestamp: u64,
+    pub session_id: String,
+    /// Hex SHA-256 of the query text
+    pub query_hash: String,
+    /// Hex SHA-256 of the text returned to the user
+    pub output_hash: String,
+    pub verdict: Verdict,
+    /// Hex SHA-256 of the model's weights, if a local model is loaded
+    pub model_hash: Option<String>,
+    /// Hex SHA-256 of the endpoint URL and model name, if the model is served by an endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_id: Option<String>,
+    /// Generation parameters the response was requested with
+    pub params: GenerationParams,
+    /// How the output was sampled
+    pub mode: GenerationMode,
+    /// Sampling seed, if one was used: the request's own or the one derived for `seeded` mode
+    pub seed: Option<u64>,
+    pub constitution_root: String,
+    /// Index of the matching entry in the interaction audit log
+    pub audit_log_index: usize,
+}
+
+impl Receipt {
+    /// Receipt for the response recorded as `entry` at `audit_log_index`
+    pub fn new(entry: &AuditEntry, params: &GenerationParams, audit_log_index: usize) -> Self {
+        Self {
+            version: RECEIPT_VERSION,
+            timestamp: entry.timestamp,
+            session_id: entry.session_id.clone(),
+            query_hash: entry.query_hash.clone(),
+            output_hash: entry.output_hash.clone(),
+            verdict: entry.verdict,
+            model_hash: entry.model_hash.clone(),
+            endpoint_id: entry.endpoint_id.clone(),
+            params: params.clone(),
+            mode: entry.mode,
+            seed: entry.seed,
+            constitution_root: entry.constitution_root.clone(),
+            audit_log_index,
+        }
+    }
+
+    /// Bytes covered by the signature: the context followed by the receipt's JSON,
+    /// fields in declaration order
+    fn signed_bytes(&self) -> Vec<u8> {
+        let json = serde_json::to_vec(self).expect("receipt serializes to JSON");
+        [RECEIPT_CONTEXT, json.as_slice()].concat()
+    }
+}
+
+/// Exported receipt: the receipt, the key that signed it and the signature
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedReceipt {
+    pub receipt: Receipt,
+    /// Hex Ed25519 public key
+    pub public_key: String,
+    /// Hex Ed25519 signature
+    pub signature: String,
+}
+
+/// Constitution root signed by the app's key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedRoot {
+    pub root: String,
+    /// Hex Ed25519 public key
+    pub public_key: String,
+    /// Hex Ed25519 signature
+    pub signature: String,
+}
+
+/// The app's Ed25519 signing key
+pub struct ReceiptSigner {
+    key: SigningKey,
+}
+
+impl ReceiptSigner {
+    /// Load the key stored at `path`, generating and saving a new one if the file
+    /// does not exist yet
+    ///
+    /// The file holds the hex secret key and is created readable by the owner only.
+    pub fn load_or_create(path: impl AsRef<Path>) -> Result<Self, KeyError> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(source) => {
+                let secret = hex::decode(source.trim())
+                    .ok()
+                    .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                    .ok_or_else(|| KeyError::Corrupt(path.display().to_string()))?;
+                Ok(Self::from_secret(&secret))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let mut secret = [0u8; 32];
+                getrandom::fill(&mut secret).map_err(|err| KeyError::Random(err.to_string()))?;
+                let signer = Self::from_secret(&secret);
+                signer.save(path)?;
+                Ok(signer)
+            }
+            Err(err) => Err(KeyError::Io(err)),
+        }
+    }
+
+    /// Signer for a known secret key
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        Self {
+            key: SigningKey::from_bytes(secret),
+        }
+    }
+
+    /// Hex public key reviewers verify against
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().to_bytes())
+    }
+
+    /// Sign a constitution root
+    pub fn sign_root(&self, root: &str) -> SignedRoot {
+        let message = [ROOT_CONTEXT, root.as_bytes()].concat();
+        SignedRoot {
+            root: root.to_string(),
+            public_key: self.public_key(),
+            signature: hex::encode(self.key.sign(&message).to_bytes()),
+        }
+    }
+
+    /// Sign a response receipt
+    pub fn sign_receipt(&self, receipt: Receipt) -> SignedReceipt {
+        let signature = self.key.sign(&receipt.signed_bytes());
+        SignedReceipt {
+            receipt,
+            public_key: self.public_key(),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), KeyError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(path)?;
+        file.write_all(hex::encode(self.key.to_bytes()).as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ReceiptSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReceiptSigner")
+            .field("public_key", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Signing key errors
+#[derive(Debug, thiserror::Error)]
+pub enum KeyError {
+    #[error("Failed to access signing key: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Signing key {0} is not a hex Ed25519 secret key")]
+    Corrupt(String),
+    #[error("Failed to generate signing key: {0}")]
+    Random(String),
+}
+
+/// Why a receipt or signed root failed to verify
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureCheckError {
+    #[error("Unsupported receipt version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Invalid public key '{0}'")]
+    InvalidPublicKey(String),
+    #[error("Signed with key {signed_by}, expected {expected}")]
+    UnexpectedKey { signed_by: String, expected: String },
+    #[error("Invalid signature encoding '{0}'")]
+    InvalidSignature(String),
+    #[error("Signature does not match the signed content")]
+    Mismatch,
+}
+
+/// Check a receipt's signature against the app's trusted hex `public_key`
+///
+/// Needs nothing but the exported receipt and the key, so reviewers can run it
+/// without the app. It does not check the output itself: compare the SHA-256
+/// of the answer text with `receipt.output_hash`.
+pub fn verify_receipt(signed: &SignedReceipt, public_key: &str) -> Result<(), SignatureCheckError> {
+    if signed.receipt.version != RECEIPT_VERSION {
+        return Err(SignatureCheckError::UnsupportedVersion(signed.receipt.version));
+    }
+    verify(&signed.receipt.signed_bytes(), &signed.public_key, &signed.signature, public_key)
+}
+
+/// Check a signed constitution root against the app's trusted hex `public_key`
+pub fn verify_root(signed: &SignedRoot, public_key: &str) -> Result<(), SignatureCheckError> {
+    let message = [ROOT_CONTEXT, signed.root.as_bytes()].concat();
+    verify(&message, &signed.public_key, &signed.signature, public_key)
+}
+
+/// Check `signature` over `message`, refusing any key but the trusted one
+fn verify(message: &[u8], signed_by: &str, signature: &str, expected: &str) -> Result<(), SignatureCheckError> {
+    if !signed_by.eq_ignore_ascii_case(expected) {
+        return Err(SignatureCheckError::UnexpectedKey {
+            signed_by: signed_by.to_string(),
+            expected: expected.to_string(),
+        });
+    }
+    let key = hex::decode(expected)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| SignatureCheckError::InvalidPublicKey(expected.to_string()))?;
+    let signature = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .map(|bytes| Signature::from_bytes(&bytes))
+        .ok_or_else(|| SignatureCheckError::InvalidSignature(signature.to_string()))?;
+    key.verify_strict(message, &signature)
+        .map_err(|_| SignatureCheckError::Mismatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda_core::constitutional_engine::Query;
+
+    fn signer() -> ReceiptSigner {
+        ReceiptSigner::from_secret(&[7; 32])
+    }
+
+    fn receipt() -> Receipt {
+        let entry = AuditEntry::new(
+            &Query::new("What is Rust?", "session", "tester"),
+            "Rust is a systems programming language.",
+            Verdict::Compliant,
+            Vec::new(),
+            "c0ffee".repeat(10) + "c0ff",
+            Some("ab".repeat(32)),
+            None,
+        )
+        .with_generation(GenerationMode::Seeded, Some(42));
+        Receipt::new(&entry, &GenerationParams::default(), 4)
+    }
+
+    #[test]
+    fn signed_receipts_verify_after_a_json_round_trip() {
+        let signer = signer();
+        let signed = signer.sign_receipt(receipt());
+        let exported = serde_json::to_string(&signed).unwrap();
+        let imported: SignedReceipt = serde_json::from_str(&exported).unwrap();
+
+        assert_eq!(imported, signed);
+        verify_receipt(&imported, &signer.public_key()).unwrap();
+        verify_receipt(&imported, &signer.public_key().to_uppercase()).unwrap();
+    }
+
+    #[test]
+    fn changing_any_field_invalidates_the_signature() {
+        let signer = signer();
+        let signed = signer.sign_receipt(receipt());
+        let edits: [fn(&mut Receipt); 12] = [
+            |r| r.timestamp += 1,
+            |r| r.session_id.push('x'),
+            |r| r.query_hash = "00".repeat(32),
+            |r| r.output_hash = "00".repeat(32),
+            |r| r.verdict = Verdict::NonCompliant,
+            |r| r.model_hash = None,
+            |r| r.endpoint_id = Some("cd".repeat(32)),
+            |r| r.params.max_tokens += 1,
+            |r| r.mode = GenerationMode::Sampled,
+            |r| r.seed = Some(43),
+            |r| r.constitution_root = "00".repeat(32),
+            |r| r.audit_log_index += 1,
+        ];
+        for (i, edit) in edits.iter().enumerate() {
+            let mut altered = signed.clone();
+            edit(&mut altered.receipt);
+            assert!(
+                matches!(verify_receipt(&altered, &signer.public_key()), Err(SignatureCheckError::Mismatch)),
+                "edit {}",
+                i
+            );
+        }
+
+        let mut altered = signed.clone();
+        altered.receipt.version += 1;
+        assert!(matches!(
+            verify_receipt(&altered, &signer.public_key()),
+            Err(SignatureCheckError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn receipts_from_another_key_are_rejected() {
+        let trusted = signer();
+        let other = ReceiptSigner::from_secret(&[8; 32]);
+        let signed = other.sign_receipt(receipt());
+        assert!(matches!(
+            verify_receipt(&signed, &trusted.public_key()),
+            Err(SignatureCheckError::UnexpectedKey { .. })
+        ));
+
+        // Claiming the trusted key does not help a signature made with another
+        let mut relabeled = signed;
+        relabeled.public_key = trusted.public_key();
+        assert!(matches!(
+            verify_receipt(&relabeled, &trusted.public_key()),
+            Err(SignatureCheckError::Mismatch)
+        ));
+    }
+
+    #[test]
+    fn root_and_receipt_signatures_are_not_interchangeable() {
+        let signer = signer();
+        let receipt = receipt();
+        let signed_root = signer.sign_root(&receipt.constitution_root);
+        verify_root(&signed_root, &signer.public_key()).unwrap();
+
+        let mut signed = signer.sign_receipt(receipt);
+        let receipt_signature = std::mem::replace(&mut signed.signature, signed_root.signature.clone());
+        assert!(matches!(
+            verify_receipt(&signed, &signer.public_key()),
+            Err(SignatureCheckError::Mismatch)
+        ));
+
+        let root_with_receipt_signature = SignedRoot {
+            signature: receipt_signature,
+            ..signed_root
+        };
+        assert!(matches!(
+            verify_root(&root_with_receipt_signature, &signer.public_key()),
+            Err(SignatureCheckError::Mismatch)
+        ));
+    }
+
+    #[test]
+    fn malformed_keys_and_signatures_are_reported() {
+        let signer = signer();
+        let mut signed = signer.sign_receipt(receipt());
+        signed.signature.truncate(10);
+        assert!(matches!(
+            verify_receipt(&signed, &signer.public_key()),
+            Err(SignatureCheckError::InvalidSignature(_))
+        ));
+
+        signed.public_key = "not a key".to_string();
+        assert!(matches!(
+            verify_receipt(&signed, "not a key"),
+            Err(SignatureCheckError::InvalidPublicKey(_))
+        ));
+    }
+}
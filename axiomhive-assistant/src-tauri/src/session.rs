@@ -0,0 +1,308 @@
+//! Session Management
+//! Per-user session identity and state, persisted across restarts
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use crate::lambda_core::violation::Violation;
+use crate::storage;
+
+/// Profile name used when the OS user cannot be determined
+const DEFAULT_USER_ID: &str = "local";
+
+/// State tracked for a single session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// Desktop profile the session belongs to
+    pub user_id: String,
+    pub created_at: u64,
+    pub last_active: u64,
+    /// Whether the Article II Section 1 disclosure has been shown in this session
+    pub disclosure_shown: bool,
+    pub counters: SessionCounters,
+}
+
+/// Per-session tallies of constitutional outcomes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionCounters {
+    pub queries: u64,
+    pub declines: u64,
+    pub clarifications: u64,
+    /// Violations reported in this session, keyed by article
+    pub violations_by_article: BTreeMap<String, u64>,
+}
+
+impl Session {
+    fn new(id: String, user_id: String) -> Self {
+        let now = unix_now();
+        Self {
+            id,
+            user_id,
+            created_at: now,
+            last_active: now,
+            disclosure_shown: false,
+            counters: SessionCounters::default(),
+        }
+    }
+}
+
+/// Session summary returned to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub user_id: String,
+    pub created_at: u64,
+    pub counters: SessionCounters,
+    /// Disclosure to display, present only the first time it is shown in the session
+    pub disclosure: Option<String>,
+}
+
+/// Creates, resumes and persists sessions
+#[derive(Debug)]
+pub struct SessionManager {
+    sessions: HashMap<String, Session>,
+    /// File the sessions are saved to; `None` keeps them in memory only
+    path: Option<PathBuf>,
+    /// Mixed into generated ids so two sessions created in the same instant differ
+    next_nonce: u64,
+}
+
+impl SessionManager {
+    /// In-memory session store
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+            path: None,
+            next_nonce: 0,
+        }
+    }
+
+    /// Load sessions saved at `path`, starting empty if the file does not exist yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SessionError> {
+        let path = path.as_ref().to_path_buf();
+        let sessions = match std::fs::read_to_string(&path) {
+            Ok(source) => serde_json::from_str::<Vec<Session>>(&source)
+                .map_err(|err| SessionError::Corrupt(err.to_string()))?
+                .into_iter()
+                .map(|session| (session.id.clone(), session))
+                .collect(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(SessionError::Io(err)),
+        };
+
+        Ok(Self {
+            sessions,
+            path: Some(path),
+            next_nonce: 0,
+        })
+    }
+
+    /// Start a new session for a desktop profile (defaults to the OS user)
+    pub fn create(&mut self, user_id: Option<String>) -> Result<&mut Session, SessionError> {
+        let user_id = user_id
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(default_user_id);
+        let id = self.generate_id(&user_id);
+        self.sessions.insert(id.clone(), Session::new(id.clone(), user_id));
+        self.save()?;
+        Ok(self.sessions.get_mut(&id).expect("session was just inserted"))
+    }
+
+    /// Resume an existing session
+    pub fn resume(&mut self, session_id: &str) -> Result<&mut Session, SessionError> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or_else(|| SessionError::NotFound(session_id.to_string()))?;
+        session.last_active = unix_now();
+        Ok(session)
+    }
+
+    /// Summary for the frontend; hands out the disclosure once per session
+    pub fn info(&mut self, session_id: &str, disclosure: &str) -> Result<SessionInfo, SessionError> {
+        let session = self.resume(session_id)?;
+        let disclosure = (!session.disclosure_shown).then(|| disclosure.to_string());
+        session.disclosure_shown = true;
+
+        let info = SessionInfo {
+            session_id: session.id.clone(),
+            user_id: session.user_id.clone(),
+            created_at: session.created_at,
+            counters: session.counters.clone(),
+            disclosure,
+        };
+        self.save()?;
+        Ok(info)
+    }
+
+    /// Record an answered query
+    pub fn record_query(&mut self, session_id: &str) -> Result<(), SessionError> {
+        self.resume(session_id)?.counters.queries += 1;
+        self.save()
+    }
+
+    /// Record a declined query and the violations behind it
+    pub fn record_decline(&mut self, session_id: &str, violations: &[Violation]) -> Result<(), SessionError> {
+        let counters = &mut self.resume(session_id)?.counters;
+        counters.queries += 1;
+        counters.declines += 1;
+        for violation in violations {
+            *counters
+                .violations_by_article
+                .entry(violation.article.clone())
+                .or_default() += 1;
+        }
+        self.save()
+    }
+
+    /// Record a query answered with clarifying questions
+    pub fn record_clarification(&mut self, session_id: &str) -> Result<(), SessionError> {
+        let counters = &mut self.resume(session_id)?.counters;
+        counters.queries += 1;
+        counters.clarifications += 1;
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), SessionError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut sessions: Vec<&Session> = self.sessions.values().collect();
+        sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        let json = serde_json::to_string_pretty(&sessions).map_err(|err| SessionError::Corrupt(err.to_string()))?;
+        storage::write_atomically(path, json.as_bytes())?;
+        Ok(())
+    }
+
+    /// Unguessable-enough local identifier derived from time, process and a counter
+    fn generate_id(&mut self, user_id: &str) -> String {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let mut hasher = Sha256::new();
+        hasher.update(nanos.to_le_bytes());
+        hasher.update(std::process::id().to_le_bytes());
+        hasher.update(self.next_nonce.to_le_bytes());
+        self.next_nonce += 1;
+        hasher.update(user_id.as_bytes());
+        hex::encode(&hasher.finalize()[..16])
+    }
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Session errors
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("Unknown session '{0}'")]
+    NotFound(String),
+    #[error("Failed to access session store: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Session store is corrupt: {0}")]
+    Corrupt(String),
+}
+
+fn default_user_id() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_USER_ID.to_string())
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lambda_core::policy::{RuleScope, Severity};
+    use crate::lambda_core::violation::MatchSpan;
+
+    const DISCLOSURE: &str = "I am an AI assistant.";
+
+    fn violation(article: &str) -> Violation {
+        Violation {
+            rule_id: format!("{}-rule", article),
+            scope: RuleScope::OutputIdentity,
+            article: article.to_string(),
+            section: "1".to_string(),
+            reference: format!("{}, Section 1", article),
+            span: MatchSpan { start: 0, end: 4 },
+            matched_text: "text".to_string(),
+            severity: Severity::High,
+            excerpt: String::new(),
+        }
+    }
+
+    #[test]
+    fn created_sessions_can_be_resumed_by_id() {
+        let mut sessions = SessionManager::new();
+        let first = sessions.create(Some("alice".to_string())).unwrap().id.clone();
+        let second = sessions.create(Some("  ".to_string())).unwrap().id.clone();
+
+        assert_ne!(first, second);
+        assert_eq!(sessions.resume(&first).unwrap().user_id, "alice");
+        assert_eq!(sessions.resume(&second).unwrap().user_id, default_user_id());
+        assert!(matches!(sessions.resume("missing"), Err(SessionError::NotFound(_))));
+    }
+
+    #[test]
+    fn the_disclosure_is_handed_out_once_per_session() {
+        let mut sessions = SessionManager::new();
+        let first = sessions.create(None).unwrap().id.clone();
+        let second = sessions.create(None).unwrap().id.clone();
+
+        assert_eq!(sessions.info(&first, DISCLOSURE).unwrap().disclosure.as_deref(), Some(DISCLOSURE));
+        assert_eq!(sessions.info(&first, DISCLOSURE).unwrap().disclosure, None);
+        assert_eq!(sessions.info(&second, DISCLOSURE).unwrap().disclosure.as_deref(), Some(DISCLOSURE));
+    }
+
+    #[test]
+    fn declines_are_counted_by_article() {
+        let mut sessions = SessionManager::new();
+        let id = sessions.create(None).unwrap().id.clone();
+        sessions.record_query(&id).unwrap();
+        sessions
+            .record_decline(&id, &[violation("Article I"), violation("Article II")])
+            .unwrap();
+        sessions.record_decline(&id, &[violation("Article I")]).unwrap();
+        sessions.record_clarification(&id).unwrap();
+
+        let counters = sessions.info(&id, DISCLOSURE).unwrap().counters;
+        assert_eq!((counters.queries, counters.declines, counters.clarifications), (4, 2, 1));
+        assert_eq!(
+            counters.violations_by_article,
+            BTreeMap::from([("Article I".to_string(), 2), ("Article II".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn sessions_reload_from_disk() {
+        let dir = std::env::temp_dir().join(format!("axiomhive-sessions-{}", std::process::id()));
+        let path = dir.join("sessions.json");
+        let mut sessions = SessionManager::load(&path).unwrap();
+        let id = sessions.create(Some("alice".to_string())).unwrap().id.clone();
+        sessions.info(&id, DISCLOSURE).unwrap();
+        sessions.record_decline(&id, &[violation("Article I")]).unwrap();
+
+        let mut reloaded = SessionManager::load(&path).unwrap();
+        let info = reloaded.info(&id, DISCLOSURE).unwrap();
+        assert_eq!(info.user_id, "alice");
+        assert_eq!(info.disclosure, None);
+        assert_eq!(info.counters.declines, 1);
+        assert_eq!(info.counters.violations_by_article["Article I"], 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+//! Local Storage
+//! Crash-safe replacement of the JSON files the app keeps its state in
+
+use std::io::Write;
+use std::path::Path;
+
+/// Replace the file at `path` with `contents`, creating its directory if needed
+///
+/// The contents are written and synced to a temporary file beside `path`, which is
+/// then renamed over it, so a crash leaves either the old file or the new one.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    std::fs::create_dir_all(dir)?;
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temp = dir.join(name);
+
+    let written = std::fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    let replaced = written.and_then(|()| std::fs::rename(&temp, path));
+    if replaced.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_replace_the_file_and_leave_nothing_beside_it() {
+        let dir = std::env::temp_dir().join(format!("axiomhive-storage-{}", std::process::id()));
+        let path = dir.join("state.json");
+
+        write_atomically(&path, b"first").unwrap();
+        write_atomically(&path, b"second").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        let names: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(names, ["state.json"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_failed_replace_keeps_what_was_there() {
+        let dir = std::env::temp_dir().join(format!("axiomhive-storage-failed-{}", std::process::id()));
+        let path = dir.join("state.json");
+        // A non-empty directory cannot be replaced by a file
+        std::fs::create_dir_all(path.join("kept")).unwrap();
+
+        assert!(write_atomically(&path, b"new").is_err());
+        assert!(path.join("kept").is_dir());
+        assert!(!dir.join("state.json.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
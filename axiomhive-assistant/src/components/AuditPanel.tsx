@@ -0,0 +1,96 @@
+import React, { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import './AuditPanel.css';
+
+export interface ConstitutionAudit {
+  root: string;
+  policy_name: string;
+  policy_version: string;
+  rule_count: number;
+  leaves: string[];
+  model_name: string | null;
+  model_hash: string | null;
+  endpoint_id: string | null;
+  public_key: string;
+}
+
+interface SignedRoot {
+  root: string;
+  public_key: string;
+  signature: string;
+}
+
+interface AuditPanelProps {
+  onClose: () => void;
+}
+
+// About/audit view: the constitution root and everything it commits to
+const AuditPanel: React.FC<AuditPanelProps> = ({ onClose }) => {
+  const [audit, setAudit] = useState<ConstitutionAudit | null>(null);
+  const [signedRoot, setSignedRoot] = useState<SignedRoot | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    Promise.all([
+      invoke<ConstitutionAudit>('get_constitution_audit'),
+      invoke<SignedRoot>('get_signed_constitution_root'),
+    ])
+      .then(([audit, signed]) => {
+        setAudit(audit);
+        setSignedRoot(signed);
+      })
+      .catch(failure => setError(failure?.message ?? String(failure)));
+  }, []);
+
+  return (
+    <div className="audit-overlay" onClick={onClose}>
+      <div className="audit-panel" onClick={event => event.stopPropagation()}>
+        <div className="audit-header">
+          <h2>About this assistant</h2>
+          <button className="audit-close-btn" onClick={onClose} aria-label="Close">×</button>
+        </div>
+        {error && <p className="audit-error">{error}</p>}
+        {audit && (
+          <>
+            <dl className="audit-fields">
+              <dt>Constitution root</dt>
+              <dd className="audit-hash">{audit.root}</dd>
+              <dt>Policy</dt>
+              <dd>{audit.policy_name} {audit.policy_version} ({audit.rule_count} rules)</dd>
+              <dt>Model</dt>
+              <dd>{audit.model_name ?? 'Not loaded'}</dd>
+              {audit.model_hash && (
+                <>
+                  <dt>Model SHA-256</dt>
+                  <dd className="audit-hash">{audit.model_hash}</dd>
+                </>
+              )}
+              {audit.endpoint_id && (
+                <>
+                  <dt>Endpoint ID (SHA-256 of URL and model name)</dt>
+                  <dd className="audit-hash">{audit.endpoint_id}</dd>
+                </>
+              )}
+              <dt>Signing key (Ed25519)</dt>
+              <dd className="audit-hash">{audit.public_key}</dd>
+              {signedRoot && (
+                <>
+                  <dt>Root signature</dt>
+                  <dd className="audit-hash">{signedRoot.signature}</dd>
+                </>
+              )}
+            </dl>
+            <details className="audit-leaves">
+              <summary>Committed leaves ({audit.leaves.length})</summary>
+              <ul>
+                {audit.leaves.map(leaf => <li key={leaf}>{leaf}</li>)}
+              </ul>
+            </details>
+          </>
+        )}
+      </div>
+    </div>
+  );
+};
+
+export default AuditPanel;
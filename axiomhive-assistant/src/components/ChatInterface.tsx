@@ -1,8 +1,11 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type Event } from '@tauri-apps/api/event';
 import MessageList from './MessageList';
 import InputBar from './InputBar';
 import ConversationHistory from './ConversationHistory';
+import AuditPanel from './AuditPanel';
+import type { SignedReceipt, Violation } from './MessageList';
 import './ChatInterface.css';
 
 interface Message {
@@ -10,28 +13,144 @@ interface Message {
   role: 'user' | 'assistant';
   content: string;
   timestamp: Date;
+  violations?: Violation[];
+  receipt?: SignedReceipt;
 }
 
+interface ClarificationRequest {
+  query: string;
+  ambiguities: Array<{
+    kind: 'unresolved_reference' | 'missing_target' | 'conflicting_instructions';
+    span: { start: number; end: number };
+    matched_text: string;
+    question: string;
+  }>;
+}
+
+interface AssistantTurn {
+  content: string;
+  declined: boolean;
+  violations: Violation[];
+  clarification: ClarificationRequest | null;
+  receipt: SignedReceipt | null;
+}
+
+interface StreamToken {
+  stream_id: string;
+  index: number;
+  text: string;
+}
+
+interface StreamComplete {
+  stream_id: string;
+  verdict: 'compliant' | 'non_compliant';
+  turn: AssistantTurn;
+}
+
+interface StreamError {
+  stream_id: string;
+  error: QueryError;
+}
+
+interface SessionInfo {
+  session_id: string;
+  user_id: string;
+  created_at: number;
+  disclosure: string | null;
+}
+
+interface QueryError {
+  message: string;
+  violations: Violation[];
+}
+
+const isQueryError = (error: unknown): error is QueryError =>
+  typeof error === 'object' && error !== null && 'message' in error && 'violations' in error;
+
+const SESSION_STORAGE_KEY = 'sessionId';
+
+// Start a streamed query, passing each chunk to onToken; resolves with the validated turn
+const streamQuery = async (
+  args: { query: string; sessionId: string; locale: string },
+  onToken: (text: string) => void,
+): Promise<AssistantTurn> => {
+  let streamId: string | null = null;
+  // Events can arrive before invoke resolves with the stream id; replay them afterwards
+  const early: Array<() => void> = [];
+  const forStream = <T extends { stream_id: string }>(handler: (payload: T) => void) =>
+    (event: Event<T>) => {
+      const run = () => {
+        if (event.payload.stream_id === streamId) handler(event.payload);
+      };
+      if (streamId === null) early.push(run);
+      else run();
+    };
+
+  let unlisten: Array<() => void> = [];
+  try {
+    return await new Promise<AssistantTurn>((resolve, reject) => {
+      Promise.all([
+        listen<StreamToken>('assistant:token', forStream(token => onToken(token.text))),
+        listen<StreamComplete>('assistant:complete', forStream(done => resolve(done.turn))),
+        listen<StreamError>('assistant:error', forStream(failed => reject(failed.error))),
+      ])
+        .then(handles => {
+          unlisten = handles;
+          return invoke<string>('stream_query', args);
+        })
+        .then(id => {
+          streamId = id;
+          early.forEach(run => run());
+        })
+        .catch(reject);
+    });
+  } finally {
+    unlisten.forEach(stop => stop());
+  }
+};
+
+// Resume the session saved in local storage, or start a new one
+const openSession = async (): Promise<SessionInfo> => {
+  const saved = localStorage.getItem(SESSION_STORAGE_KEY);
+  if (saved) {
+    try {
+      return await invoke<SessionInfo>('resume_session', { sessionId: saved });
+    } catch (error) {
+      console.warn('Could not resume session, starting a new one:', error);
+    }
+  }
+  const session = await invoke<SessionInfo>('create_session', {});
+  localStorage.setItem(SESSION_STORAGE_KEY, session.session_id);
+  return session;
+};
+
 const ChatInterface: React.FC = () => {
+  const [sessionId, setSessionId] = useState<string | null>(null);
   const [messages, setMessages] = useState<Message[]>([]);
   const [isLoading, setIsLoading] = useState(false);
   const [isHistoryVisible, setIsHistoryVisible] = useState(true);
+  const [isAuditVisible, setIsAuditVisible] = useState(false);
 
   useEffect(() => {
-    // Add constitutional disclosure on first load
-    if (messages.length === 0) {
-      const disclosureMessage: Message = {
-        id: 'disclosure',
-        role: 'assistant',
-        content: "I am an AI, a computational tool. I do not have consciousness, feelings, or a personal identity. I am bound by the Constitution of a Deterministic Assistant (CDA-v1.0) to ensure transparency, safety, and deterministic operation.",
-        timestamp: new Date(),
-      };
-      setMessages([disclosureMessage]);
-    }
+    // Open the session; the backend hands out the constitutional disclosure once per session
+    openSession()
+      .then(session => {
+        setSessionId(session.session_id);
+        if (session.disclosure) {
+          const disclosureMessage: Message = {
+            id: 'disclosure',
+            role: 'assistant',
+            content: session.disclosure,
+            timestamp: new Date(),
+          };
+          setMessages([disclosureMessage]);
+        }
+      })
+      .catch(error => console.error('Error opening session:', error));
   }, []);
 
   const handleSendMessage = async (content: string) => {
-    if (!content.trim()) return;
+    if (!content.trim() || !sessionId) return;
 
     const userMessage: Message = {
       id: `user-${Date.now()}`,
@@ -40,34 +159,55 @@ const ChatInterface: React.FC = () => {
       timestamp: new Date(),
     };
 
-    setMessages(prev => [...prev, userMessage]);
+    const assistantId = `assistant-${Date.now()}`;
+    const updateAssistant = (update: (message: Message) => Message) =>
+      setMessages(prev => prev.map(message => (message.id === assistantId ? update(message) : message)));
+
+    setMessages(prev => [
+      ...prev,
+      userMessage,
+      { id: assistantId, role: 'assistant', content: '', timestamp: new Date() },
+    ]);
     setIsLoading(true);
 
     try {
-      const response = await invoke<string>('process_query', { query: content });
-
-      const assistantMessage: Message = {
-        id: `assistant-${Date.now()}`,
-        role: 'assistant',
-        content: response,
-        timestamp: new Date(),
-      };
+      const turn = await streamQuery(
+        { query: content, sessionId, locale: navigator.language },
+        text => updateAssistant(message => ({ ...message, content: message.content + text })),
+      );
 
-      setMessages(prev => [...prev, assistantMessage]);
+      // The validated turn is authoritative: a declined output replaces the streamed text
+      updateAssistant(message => ({
+        ...message,
+        content: turn.content,
+        violations: turn.declined ? turn.violations : undefined,
+        receipt: turn.receipt ?? undefined,
+      }));
     } catch (error) {
       console.error('Error sending message:', error);
-      const errorMessage: Message = {
-        id: `error-${Date.now()}`,
-        role: 'assistant',
-        content: 'Sorry, I encountered an error processing your request.',
-        timestamp: new Date(),
-      };
-      setMessages(prev => [...prev, errorMessage]);
+      updateAssistant(message => ({
+        ...message,
+        content: isQueryError(error)
+          ? error.message
+          : 'Sorry, I encountered an error processing your request.',
+        violations: isQueryError(error) ? error.violations : undefined,
+      }));
     } finally {
       setIsLoading(false);
     }
   };
 
+  // The session is kept; only the history the model sees is cleared
+  const handleNewConversation = async () => {
+    if (!sessionId) return;
+    try {
+      await invoke('reset_conversation', { sessionId });
+      setMessages([]);
+    } catch (error) {
+      console.error('Error starting a new conversation:', error);
+    }
+  };
+
   return (
     <div className="chat-interface">
       <ConversationHistory
@@ -81,6 +221,14 @@ const ChatInterface: React.FC = () => {
             {isHistoryVisible ? '‹' : '›'}
           </button>
           <h1>AxiomHive Assistant</h1>
+          <div className="chat-header-actions">
+            <button className="about-btn" onClick={() => setIsAuditVisible(true)}>
+              About
+            </button>
+            <button className="new-conversation-btn" onClick={handleNewConversation} disabled={isLoading}>
+              New conversation
+            </button>
+          </div>
         </div>
 
         <MessageList messages={messages} />
@@ -91,6 +239,7 @@ const ChatInterface: React.FC = () => {
           placeholder={isLoading ? "Processing..." : "Type your message..."}
         />
       </div>
+      {isAuditVisible && <AuditPanel onClose={() => setIsAuditVisible(false)} />}
     </div>
   );
 };
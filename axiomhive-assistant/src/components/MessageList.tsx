@@ -1,13 +1,54 @@
 import React from 'react';
 import './MessageList.css';
 
+export interface Violation {
+  rule_id: string;
+  scope: string;
+  article: string;
+  section: string;
+  reference: string;
+  span: { start: number; end: number };
+  matched_text: string;
+  severity: 'low' | 'medium' | 'high' | 'critical';
+  excerpt: string;
+}
+
+// Signed record of what a response was produced from; verifiable offline
+export interface SignedReceipt {
+  receipt: {
+    version: number;
+    timestamp: number;
+    session_id: string;
+    query_hash: string;
+    output_hash: string;
+    verdict: 'compliant' | 'non_compliant';
+    model_hash: string | null;
+    endpoint_id?: string;
+    params: Record<string, unknown>;
+    mode: 'greedy' | 'seeded' | 'sampled';
+    seed: number | null;
+    constitution_root: string;
+    audit_log_index: number;
+  };
+  public_key: string;
+  signature: string;
+}
+
 interface Message {
   id: string;
   role: 'user' | 'assistant';
   content: string;
   timestamp: Date;
+  violations?: Violation[];
+  receipt?: SignedReceipt;
 }
 
+// Copy the receipt in its export format, ready to save as receipt.json
+const copyReceipt = (receipt: SignedReceipt) =>
+  navigator.clipboard
+    .writeText(JSON.stringify(receipt, null, 2))
+    .catch(error => console.error('Error copying receipt:', error));
+
 interface MessageListProps {
   messages: Message[];
 }
@@ -23,7 +64,25 @@ const MessageList: React.FC<MessageListProps> = ({ messages }) => {
           <div className="message-content">
             {message.content}
           </div>
+          {message.violations && message.violations.length > 0 && (
+            <ul className="message-violations">
+              {message.violations.map((violation, index) => (
+                <li key={`${violation.rule_id}-${index}`} className={`violation severity-${violation.severity}`}>
+                  <div className="violation-reference">
+                    {violation.reference} <span className="violation-rule">({violation.rule_id})</span>
+                  </div>
+                  <div className="violation-match">Matched: “{violation.matched_text}”</div>
+                  <div className="violation-excerpt">{violation.excerpt}</div>
+                </li>
+              ))}
+            </ul>
+          )}
           <div className="message-timestamp">
+            {message.receipt && (
+              <button className="receipt-btn" onClick={() => message.receipt && copyReceipt(message.receipt)}>
+                Copy receipt
+              </button>
+            )}
             {message.timestamp.toLocaleTimeString()}
           </div>
         </div>